@@ -1,25 +1,91 @@
+use std::str::FromStr;
+
 use alloy::primitives::Address;
 use axum::extract::{FromRequestParts, Path};
 use axum::http::request::Parts;
 use axum::http::StatusCode;
 
+use crate::supported_networks::SupportedNetworks;
+
 pub struct EthereumAddress(pub Address);
 
+/// A wallet given either as a plain address or as a CAIP-10 account id (`eip155:1:0x…`), which
+/// also names its network.
+pub struct AccountId {
+    pub address: Address,
+    pub network: Option<SupportedNetworks>
+}
+
+impl FromStr for AccountId {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((chain, address)) = s.rsplit_once(':') else {
+            let address = s.parse::<Address>().map_err(|_| "Invalid Ethereum address format")?;
+
+            return Ok(AccountId { address, network: None });
+        };
+
+        let chain_id = chain.strip_prefix("eip155:").ok_or("Unsupported CAIP-10 namespace")?;
+
+        let network = chain_id.parse::<u64>().ok()
+            .and_then(SupportedNetworks::from_chain_id)
+            .ok_or("Unsupported CAIP-10 chain id")?;
+
+        let address = address.parse::<Address>().map_err(|_| "Invalid Ethereum address format")?;
+
+        Ok(AccountId { address, network: Some(network) })
+    }
+}
+
 #[async_trait::async_trait]
-impl<S> FromRequestParts<S> for EthereumAddress
+impl<S> FromRequestParts<S> for AccountId
     where
         S: Send + Sync,
 {
     type Rejection = (StatusCode, &'static str);
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let Path(address) = Path::<String>::from_request_parts(parts, state).await.map_err(|_| {
+        let Path(account_id) = Path::<String>::from_request_parts(parts, state).await.map_err(|_| {
             (StatusCode::BAD_REQUEST, "Invalid path parameter")
         })?;
 
-        match address.parse::<Address>() {
-            Ok(parsed_address) => Ok(EthereumAddress(parsed_address)),
-            Err(_) => Err((StatusCode::BAD_REQUEST, "Invalid Ethereum address format")),
-        }
+        account_id.parse::<AccountId>().map_err(|err| (StatusCode::BAD_REQUEST, err))
+    }
+}
+
+#[async_trait::async_trait]
+impl<S> FromRequestParts<S> for EthereumAddress
+    where
+        S: Send + Sync,
+{
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let account_id = AccountId::from_request_parts(parts, state).await?;
+
+        Ok(EthereumAddress(account_id.address))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+
+    use crate::extractors::ethereum_address::AccountId;
+    use crate::supported_networks::SupportedNetworks;
+
+    #[test]
+    fn test_parse_account_id() {
+        let wallet = address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045");
+
+        let account_id = "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045".parse::<AccountId>().unwrap();
+        assert_eq!((account_id.address, account_id.network), (wallet, None));
+
+        let account_id = "eip155:137:0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045".parse::<AccountId>().unwrap();
+        assert_eq!((account_id.address, account_id.network), (wallet, Some(SupportedNetworks::Polygon)));
+
+        assert!("eip155:10:0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045".parse::<AccountId>().is_err());
+        assert!("cosmos:cosmoshub-3:cosmos1t2uflqwqe0fsj0shcfkrvpukewcw40yjj6hdc0".parse::<AccountId>().is_err());
     }
-}
\ No newline at end of file
+}
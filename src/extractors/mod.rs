@@ -1 +1,2 @@
-pub mod ethereum_address;
\ No newline at end of file
+pub mod ethereum_address;
+pub mod session;
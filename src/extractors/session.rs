@@ -0,0 +1,38 @@
+use std::sync::Arc;
+
+use axum::extract::{FromRef, FromRequestParts};
+use axum::http::header::AUTHORIZATION;
+use axum::http::request::Parts;
+use axum::http::StatusCode;
+
+use crate::services::siwe::{Session, SessionService};
+
+pub struct WalletSession {
+    pub token: String,
+    pub session: Session
+}
+
+#[async_trait::async_trait]
+impl<S> FromRequestParts<S> for WalletSession
+    where
+        Arc<SessionService>: FromRef<S>,
+        S: Send + Sync,
+{
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let session_service = Arc::<SessionService>::from_ref(state);
+
+        let token = parts.headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim)
+            .ok_or((StatusCode::UNAUTHORIZED, "Missing session token"))?;
+
+        match session_service.session(token).await {
+            Some(session) => Ok(WalletSession { token: token.to_string(), session }),
+            None => Err((StatusCode::UNAUTHORIZED, "Invalid or expired session")),
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use alloy::primitives::Bytes;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use log::info;
+use serde::Deserialize;
+
+use crate::extractors::session::WalletSession;
+use crate::response::auth::{NonceResponse, SessionResponse};
+use crate::response::error::AppResult;
+use crate::services::siwe::{self, Session, SessionService};
+
+#[allow(clippy::missing_errors_doc)]
+pub async fn nonce(State(session_service): State<Arc<SessionService>>) -> AppResult<Json<NonceResponse>> {
+    let nonce = session_service.issue_nonce().await;
+
+    Ok(Json(NonceResponse { nonce }))
+}
+
+#[derive(Deserialize)]
+pub struct VerifyParams {
+    message: String,
+    signature: Bytes
+}
+
+pub async fn verify(State(session_service): State<Arc<SessionService>>, Json(params): Json<VerifyParams>) -> Response {
+    match session_service.sign_in(&params.message, &params.signature).await {
+        Ok((token, session)) => {
+            info!(target: "API", "Signed in {} on chain {}", session.address, session.chain_id);
+
+            Json(SessionResponse { token, session }).into_response()
+        }
+        Err(err) => {
+            let status = match err {
+                siwe::Error::NotConfigured => StatusCode::SERVICE_UNAVAILABLE,
+                siwe::Error::Malformed(_) | siwe::Error::UnsupportedVersion(_) | siwe::Error::UnsupportedChain(_) => StatusCode::BAD_REQUEST,
+                siwe::Error::DomainMismatch(_) | siwe::Error::InvalidNonce | siwe::Error::Expired | siwe::Error::NotYetValid | siwe::Error::InvalidSignature => StatusCode::UNAUTHORIZED,
+            };
+
+            (status, err.to_string()).into_response()
+        }
+    }
+}
+
+#[allow(clippy::missing_errors_doc)]
+pub async fn session(WalletSession { session, .. }: WalletSession) -> AppResult<Json<Session>> {
+    Ok(Json(session))
+}
+
+pub async fn logout(State(session_service): State<Arc<SessionService>>, WalletSession { token, .. }: WalletSession) -> Response {
+    session_service.sign_out(&token).await;
+
+    (StatusCode::OK, "Signed out").into_response()
+}
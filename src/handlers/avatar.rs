@@ -1,29 +1,264 @@
 use std::sync::Arc;
+
+use alloy::primitives::{Address, Bytes, U256};
 use axum::extract::{Query, State};
+use axum::http::header::{CACHE_CONTROL, CONTENT_TYPE, LOCATION};
+use axum::http::StatusCode;
 use axum::Json;
-use serde::Deserialize;
+use axum::response::{IntoResponse, Response};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
 
-use crate::extractors::ethereum_address::EthereumAddress;
-use crate::response::avatar::AvatarInfoWithMetadataResponse;
+use crate::extractors::ethereum_address::{AccountId, EthereumAddress};
 use crate::response::error::AppResult;
-use crate::services::avatar::AvatarService;
+use crate::response::fields::Fields;
+use crate::response::page::PageParams;
+use crate::services::avatar::{AvatarService, DEFAULT_AVATAR_URL};
+use crate::services::blockies;
+use crate::services::events::EventIndex;
+use crate::services::primary::PRIMARY_POLICY;
+use crate::services::rpc::{self, BlockSelector};
+use crate::services::signed_avatar::{self, SetAvatar};
 use crate::supported_networks::SupportedNetworks;
 
+// Avatars change rarely, but image tags shouldn't show a replaced one for long
+const REDIRECT_CACHE_CONTROL: &str = "public, max-age=300";
+
+#[derive(Deserialize)]
+pub enum Resolve {
+    #[serde(rename = "primary")]
+    Primary
+}
+
 #[derive(Deserialize)]
 pub struct GetParams {
     metadata: Option<bool>,
-    network: Option<SupportedNetworks>
+    // Comma separated names, chain ids or CAIP-2 ids, `network` is kept as an alias
+    networks: Option<String>,
+    network: Option<String>,
+    block: Option<u64>,
+    at: Option<u64>,
+    resolve: Option<Resolve>,
+    // Overrides of the primary avatar policy, comma separated
+    policy: Option<String>,
+    priority: Option<String>,
+    // Comma separated avatar fields to return, e.g. `avatar,uri`
+    fields: Option<String>
+}
+
+#[allow(clippy::missing_errors_doc)]
+pub async fn get(State(avatar_service): State<Arc<AvatarService>>, AccountId { address, network }: AccountId, Query(params): Query<GetParams>) -> AppResult<Response> {
+    let block = match (params.block, params.at) {
+        (Some(_), Some(_)) => return Ok((StatusCode::BAD_REQUEST, "Use either block or at, not both").into_response()),
+        (Some(number), None) => Some(BlockSelector::Number(number)),
+        (None, Some(timestamp)) => Some(BlockSelector::Timestamp(timestamp)),
+        (None, None) => None
+    };
+
+    // The primary avatar is ranked by its token metadata, e.g. whether its collection is verified
+    if params.resolve.is_some() && params.metadata == Some(false) {
+        return Ok((StatusCode::BAD_REQUEST, "resolve=primary needs the token metadata, drop metadata=false").into_response());
+    }
+
+    let networks = match select_networks(params.networks.as_deref().or(params.network.as_deref()), network) {
+        Ok(networks) => networks,
+        Err(response) => return Ok(response)
+    };
+
+    let blocks = match block {
+        None => None,
+        Some(selector) => match avatar_service.resolve_blocks(&networks, selector).await {
+            Ok(blocks) => Some(blocks),
+            Err(err) => {
+                let status = block_error_status(&err);
+
+                if status == StatusCode::BAD_GATEWAY {
+                    error!(target: "API", "Failed to resolve block {selector:?}: {err}");
+                }
+
+                return Ok((status, err.to_string()).into_response());
+            }
+        }
+    };
+
+    if let Some(Resolve::Primary) = params.resolve {
+        let policy = match PRIMARY_POLICY.with_overrides(params.policy.as_deref(), params.priority.as_deref()) {
+            Ok(policy) => policy,
+            Err(err) => return Ok((StatusCode::BAD_REQUEST, err.to_string()).into_response())
+        };
+
+        let response = avatar_service.get_primary(&address, networks, blocks.as_ref(), &policy).await?;
+
+        return partial(response, params.fields.as_deref());
+    }
+
+    // Skips the token metadata and its IPFS lookups
+    if params.metadata == Some(false) {
+        let response = avatar_service.get_info(&address, networks, blocks.as_ref()).await?;
+
+        return partial(response, params.fields.as_deref());
+    }
+
+    let response = avatar_service.get_info_with_metadata(&address, networks, blocks.as_ref()).await?;
+
+    partial(response, params.fields.as_deref())
+}
+
+#[derive(Deserialize)]
+pub struct RedirectParams {
+    networks: Option<String>
 }
 
+/// Redirects to the avatar image so it can be used as a plain `<img src>`, falling back to
+/// `DEFAULT_AVATAR_URL` or the wallet's identicon.
 #[allow(clippy::missing_errors_doc)]
-pub async fn get(State(avatar_service): State<Arc<AvatarService>>, EthereumAddress(address): EthereumAddress, Query(params): Query<GetParams>) -> AppResult<Json<AvatarInfoWithMetadataResponse>> {
-    let networks = if let Some(network) = params.network {
-        vec![network]
-    } else {
-        SupportedNetworks::all()
+pub async fn redirect(State(avatar_service): State<Arc<AvatarService>>, AccountId { address, network }: AccountId, Query(params): Query<RedirectParams>) -> AppResult<Response> {
+    let networks = match select_networks(params.networks.as_deref(), network) {
+        Ok(networks) => networks,
+        Err(response) => return Ok(response)
+    };
+
+    let image_url = avatar_service.get_image_url(&address, networks).await?;
+
+    // Without a configured default, wallets get their blockies identicon
+    let location = image_url
+        .or_else(|| DEFAULT_AVATAR_URL.clone())
+        .unwrap_or_else(|| blockies::path(&address));
+
+    Ok((StatusCode::FOUND, [(LOCATION, location), (CACHE_CONTROL, REDIRECT_CACHE_CONTROL.to_string())]).into_response())
+}
+
+/// Deterministic blockies identicon of the wallet, the placeholder for wallets without an avatar.
+pub async fn fallback_svg(EthereumAddress(address): EthereumAddress) -> Response {
+    let headers = [(CONTENT_TYPE, "image/svg+xml"), (CACHE_CONTROL, "public, max-age=31536000, immutable")];
+
+    (headers, blockies::svg(&address)).into_response()
+}
+
+// A selector before genesis can never resolve, a block past the tip may exist later
+fn block_error_status(err: &eyre::Report) -> StatusCode {
+    match err.downcast_ref::<rpc::Error>() {
+        Some(rpc::Error::BeforeGenesis) => StatusCode::BAD_REQUEST,
+        Some(rpc::Error::UnknownBlock) => StatusCode::NOT_FOUND,
+        _ => StatusCode::BAD_GATEWAY
+    }
+}
+
+fn select_networks(networks: Option<&str>, account_network: Option<SupportedNetworks>) -> Result<Vec<SupportedNetworks>, Response> {
+    match networks.map(SupportedNetworks::parse_list) {
+        Some(Ok(networks)) if !networks.is_empty() => Ok(networks),
+        Some(Err(err)) => Err((StatusCode::BAD_REQUEST, err.to_string()).into_response()),
+        // A CAIP-10 wallet id scopes the lookup to its chain
+        _ => Ok(account_network.map_or_else(SupportedNetworks::all, |network| vec![network]))
+    }
+}
+
+fn partial(response: impl Serialize, fields: Option<&str>) -> AppResult<Response> {
+    let Some(fields) = fields else {
+        return Ok(Json(response).into_response());
+    };
+
+    let mut response = serde_json::to_value(response)?;
+    Fields::new(fields).apply(&mut response);
+
+    Ok(Json(response).into_response())
+}
+
+#[derive(Deserialize)]
+pub struct HistoryParams {
+    network: String
+}
+
+/// Indexed `AvatarSet` events of the wallet on one network. Answers 501 when the event follower
+/// doesn't index the network (`EVENT_POLL_INTERVAL=0` or no deploy block) and 503 until its first sync.
+pub async fn history(State(avatar_service): State<Arc<AvatarService>>, EthereumAddress(address): EthereumAddress, Query(params): Query<HistoryParams>, Query(page): Query<PageParams>) -> Response {
+    let Ok(network) = params.network.parse::<SupportedNetworks>() else {
+        return (StatusCode::BAD_REQUEST, "Unknown network").into_response();
+    };
+
+    if !EventIndex::is_enabled(&network) {
+        return (StatusCode::NOT_IMPLEMENTED, "Avatar event indexing is disabled").into_response();
+    }
+
+    if !avatar_service.events.is_indexed(&network).await {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Avatar events are not indexed yet").into_response();
+    }
+
+    let response = avatar_service.events.history(&network, &address, &page).await;
+
+    Json(response).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct SignedParams {
+    wallet: Address,
+    chain_id: u64,
+    token_address: Address,
+    token_id: String,
+    nonce: String,
+    expiry: u64,
+    signature: Bytes
+}
+
+impl SignedParams {
+    pub(crate) fn into_message(self) -> Result<(SetAvatar, Bytes), Response> {
+        let (Ok(token_id), Ok(nonce)) = (self.token_id.parse::<U256>(), self.nonce.parse::<U256>()) else {
+            return Err((StatusCode::BAD_REQUEST, "Invalid token id or nonce").into_response());
+        };
+
+        let message = SetAvatar {
+            wallet: self.wallet,
+            chainId: U256::from(self.chain_id),
+            tokenAddress: self.token_address,
+            tokenId: token_id,
+            nonce,
+            expiry: U256::from(self.expiry),
+        };
+
+        Ok((message, self.signature))
+    }
+}
+
+pub async fn set_signed(State(avatar_service): State<Arc<AvatarService>>, Json(params): Json<SignedParams>) -> Response {
+    let (message, signature) = match params.into_message() {
+        Ok(message) => message,
+        Err(response) => return response
     };
-    
-    let response = avatar_service.get_info_with_metadata(&address, networks).await?;
 
-    Ok(Json(response))
+    let (wallet, token_address, token_id) = (message.wallet, message.tokenAddress, message.tokenId);
+
+    match avatar_service.signed_avatars.submit(message, signature).await {
+        Ok(network) => {
+            info!(target: "API", "Stored signed avatar {token_address}/{token_id} for {wallet} on {network}");
+
+            (StatusCode::OK, "Stored signed avatar").into_response()
+        }
+        Err(err) => {
+            let status = match err {
+                signed_avatar::Error::UnsupportedChain(_) | signed_avatar::Error::Expired => StatusCode::BAD_REQUEST,
+                signed_avatar::Error::StaleNonce => StatusCode::CONFLICT,
+                signed_avatar::Error::InvalidSignature => StatusCode::UNAUTHORIZED,
+                signed_avatar::Error::NotOwner => StatusCode::FORBIDDEN,
+                signed_avatar::Error::Ownership(_) => StatusCode::BAD_GATEWAY,
+                signed_avatar::Error::Persist(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+
+            (status, err.to_string()).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+
+    use crate::handlers::avatar::block_error_status;
+    use crate::services::rpc;
+
+    #[test]
+    fn test_block_error_status() {
+        assert_eq!(block_error_status(&rpc::Error::BeforeGenesis.into()), StatusCode::BAD_REQUEST);
+        assert_eq!(block_error_status(&rpc::Error::UnknownBlock.into()), StatusCode::NOT_FOUND);
+        assert_eq!(block_error_status(&eyre::eyre!("connection refused")), StatusCode::BAD_GATEWAY);
+    }
 }
@@ -0,0 +1,13 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+use crate::services::avatar::AvatarService;
+
+pub async fn purge(State(avatar_service): State<Arc<AvatarService>>) -> Response {
+    avatar_service.purge_caches().await;
+
+    (StatusCode::OK, "Purged caches").into_response()
+}
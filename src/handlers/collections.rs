@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use alloy::primitives::{Address, U256};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+
+use crate::response::page::PageParams;
+use crate::services::avatar::AvatarService;
+use crate::services::events::EventIndex;
+use crate::supported_networks::SupportedNetworks;
+
+pub async fn users(State(avatar_service): State<Arc<AvatarService>>, Path((network, contract)): Path<(String, String)>, Query(page): Query<PageParams>) -> Response {
+    lookup_users(&avatar_service, &network, &contract, None, &page).await
+}
+
+pub async fn token_users(State(avatar_service): State<Arc<AvatarService>>, Path((network, contract, token_id)): Path<(String, String, String)>, Query(page): Query<PageParams>) -> Response {
+    let Ok(token_id) = token_id.parse::<U256>() else {
+        return (StatusCode::BAD_REQUEST, "Invalid token id").into_response();
+    };
+
+    lookup_users(&avatar_service, &network, &contract, Some(token_id), &page).await
+}
+
+// Answers 501 when the network's events are not indexed (`EVENT_POLL_INTERVAL=0` or no deploy
+// block) and 503 until its first sync
+async fn lookup_users(avatar_service: &AvatarService, network: &str, contract: &str, token_id: Option<U256>, page: &PageParams) -> Response {
+    let Ok(network) = network.parse::<SupportedNetworks>() else {
+        return (StatusCode::NOT_FOUND, "Unknown network").into_response();
+    };
+
+    let Ok(contract) = contract.parse::<Address>() else {
+        return (StatusCode::BAD_REQUEST, "Invalid Ethereum address format").into_response();
+    };
+
+    if !EventIndex::is_enabled(&network) {
+        return (StatusCode::NOT_IMPLEMENTED, "Avatar event indexing is disabled").into_response();
+    }
+
+    if !avatar_service.events.is_indexed(&network).await {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Avatar events are not indexed yet").into_response();
+    }
+
+    let response = avatar_service.events.users(&network, &contract, token_id, page).await;
+
+    Json(response).into_response()
+}
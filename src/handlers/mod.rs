@@ -1,2 +1,11 @@
+pub mod auth;
 pub mod avatar;
-pub mod whitelist;
\ No newline at end of file
+pub mod cache;
+pub mod collections;
+pub mod moderation;
+pub mod nft;
+pub mod relay;
+pub mod stats;
+pub mod stream;
+pub mod webhook;
+pub mod whitelist;
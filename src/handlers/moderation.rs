@@ -0,0 +1,111 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use alloy::primitives::{Address, U256};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use log::{error, info};
+use serde::Deserialize;
+
+use crate::response::error::AppResult;
+use crate::response::moderation::BlockedContractResponse;
+use crate::services::avatar::AvatarService;
+use crate::supported_networks::SupportedNetworks;
+
+#[allow(clippy::missing_errors_doc)]
+pub async fn get(State(avatar_service): State<Arc<AvatarService>>) -> AppResult<Json<Vec<BlockedContractResponse>>> {
+    let response = avatar_service.moderation.list().await;
+
+    Ok(Json(response))
+}
+
+#[derive(Deserialize)]
+pub struct BlockParams {
+    network: String,
+    contract: Address,
+    // Applies to the whole contract when omitted
+    token_ids: Option<Vec<String>>,
+    reason: Option<String>
+}
+
+fn parse_token_ids(token_ids: Option<Vec<String>>) -> Result<Option<BTreeSet<U256>>, Response> {
+    let Some(token_ids) = token_ids else {
+        return Ok(None);
+    };
+
+    if token_ids.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "token_ids must not be empty").into_response());
+    }
+
+    token_ids.iter()
+        .map(|token_id| token_id.parse::<U256>())
+        .collect::<Result<BTreeSet<U256>, _>>()
+        .map(Some)
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid token id").into_response())
+}
+
+pub async fn block(State(avatar_service): State<Arc<AvatarService>>, Json(params): Json<BlockParams>) -> Response {
+    let Ok(network) = params.network.parse::<SupportedNetworks>() else {
+        return (StatusCode::BAD_REQUEST, "Unknown network").into_response();
+    };
+
+    let token_ids = match parse_token_ids(params.token_ids) {
+        Ok(token_ids) => token_ids,
+        Err(response) => return response
+    };
+
+    info!(target: "API", "Blocking {} on {network} (token ids: {token_ids:?})", params.contract);
+
+    if let Err(err) = avatar_service.moderation.block(network, params.contract, token_ids, params.reason).await {
+        error!(target: "API", "{err}");
+
+        return (StatusCode::INTERNAL_SERVER_ERROR, "Blocked until the next restart, the block could not be persisted").into_response();
+    }
+
+    (StatusCode::OK, "Blocked").into_response()
+}
+
+#[derive(Deserialize)]
+pub struct UnblockParams {
+    network: String,
+    contract: Address,
+    token_ids: Option<Vec<String>>
+}
+
+pub async fn unblock(State(avatar_service): State<Arc<AvatarService>>, Json(params): Json<UnblockParams>) -> Response {
+    let Ok(network) = params.network.parse::<SupportedNetworks>() else {
+        return (StatusCode::BAD_REQUEST, "Unknown network").into_response();
+    };
+
+    let token_ids = match parse_token_ids(params.token_ids) {
+        Ok(token_ids) => token_ids,
+        Err(response) => return response
+    };
+
+    match avatar_service.moderation.unblock(&network, &params.contract, token_ids.as_ref()).await {
+        Ok(true) => {
+            info!(target: "API", "Unblocked {} on {network} (token ids: {token_ids:?})", params.contract);
+
+            (StatusCode::OK, "Unblocked").into_response()
+        }
+        Ok(false) => (StatusCode::NOT_FOUND, "No matching manual block").into_response(),
+        Err(err) => {
+            error!(target: "API", "{err}");
+
+            (StatusCode::INTERNAL_SERVER_ERROR, "Unblocked until the next restart, the change could not be persisted").into_response()
+        }
+    }
+}
+
+pub async fn reload(State(avatar_service): State<Arc<AvatarService>>) -> Response {
+    match avatar_service.moderation.reload().await {
+        Ok(warnings) => (StatusCode::OK, Json(warnings)).into_response(),
+        Err(err) => {
+            error!(target: "API", "Failed to reload blocklist: {err}");
+
+            (StatusCode::BAD_GATEWAY, err.to_string()).into_response()
+        }
+    }
+}
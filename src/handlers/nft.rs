@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use alloy::primitives::{Address, U256};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use log::error;
+
+use crate::services::avatar::AvatarService;
+use crate::services::rpc;
+use crate::supported_networks::SupportedNetworks;
+
+pub async fn get(State(avatar_service): State<Arc<AvatarService>>, Path((network, contract, token_id)): Path<(String, String, String)>) -> Response {
+    let Ok(network) = network.parse::<SupportedNetworks>() else {
+        return (StatusCode::NOT_FOUND, "Unknown network").into_response();
+    };
+
+    let Ok(contract) = contract.parse::<Address>() else {
+        return (StatusCode::BAD_REQUEST, "Invalid Ethereum address format").into_response();
+    };
+
+    let Ok(token_id) = token_id.parse::<U256>() else {
+        return (StatusCode::BAD_REQUEST, "Invalid token id").into_response();
+    };
+
+    match avatar_service.get_nft(&network, &contract, token_id).await {
+        Ok(nft_info) => Json(nft_info).into_response(),
+        Err(err) => {
+            let status = lookup_error_status(&err);
+
+            if status == StatusCode::BAD_GATEWAY {
+                error!(target: "API", "Failed to look up {contract}/{token_id} on {network}: {err}");
+
+                return (status, "Failed to look up token").into_response();
+            }
+
+            (status, "Token not found").into_response()
+        }
+    }
+}
+
+// Only a contract that isn't an NFT or a reverted call say the token doesn't exist, failed calls
+// may succeed on a retry
+fn lookup_error_status(err: &eyre::Report) -> StatusCode {
+    let not_found = matches!(err.downcast_ref::<rpc::Error>(), Some(rpc::Error::UnsupportedToken))
+        || err.downcast_ref::<alloy::contract::Error>().is_some_and(rpc::is_revert);
+
+    if not_found {
+        StatusCode::NOT_FOUND
+    } else {
+        StatusCode::BAD_GATEWAY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::transports::{RpcError, TransportErrorKind};
+    use axum::http::StatusCode;
+
+    use crate::handlers::nft::lookup_error_status;
+    use crate::services::rpc;
+
+    fn error_response(code: i64, message: &str) -> eyre::Report {
+        let payload = serde_json::from_value(serde_json::json!({ "code": code, "message": message })).unwrap();
+
+        alloy::contract::Error::TransportError(RpcError::ErrorResp(payload)).into()
+    }
+
+    #[test]
+    fn test_lookup_error_status() {
+        assert_eq!(lookup_error_status(&rpc::Error::UnsupportedToken.into()), StatusCode::NOT_FOUND);
+        assert_eq!(lookup_error_status(&error_response(3, "execution reverted: ERC721: invalid token ID")), StatusCode::NOT_FOUND);
+        assert_eq!(lookup_error_status(&error_response(-32000, "execution reverted")), StatusCode::NOT_FOUND);
+
+        // Provider errors come as error responses as well
+        assert_eq!(lookup_error_status(&error_response(-32005, "rate limit exceeded")), StatusCode::BAD_GATEWAY);
+        assert_eq!(lookup_error_status(&error_response(-32000, "header not found")), StatusCode::BAD_GATEWAY);
+
+        let timeout = alloy::contract::Error::TransportError(TransportErrorKind::custom_str("Request timed out"));
+        assert_eq!(lookup_error_status(&timeout.into()), StatusCode::BAD_GATEWAY);
+        assert_eq!(lookup_error_status(&eyre::eyre!("connection refused")), StatusCode::BAD_GATEWAY);
+    }
+}
@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use axum::response::{IntoResponse, Response};
+use log::info;
+
+use crate::handlers::avatar::SignedParams;
+use crate::services::relayer::{self, RelayerService};
+
+pub async fn relay(State(relayer_service): State<Arc<RelayerService>>, Json(params): Json<SignedParams>) -> Response {
+    let (intent, signature) = match params.into_message() {
+        Ok(message) => message,
+        Err(response) => return response
+    };
+
+    match relayer_service.relay(intent, signature).await {
+        Ok(job) => {
+            info!(target: "API", "Submitted relay {} for {} on {}", job.id, job.wallet, job.network);
+
+            (StatusCode::ACCEPTED, Json(job)).into_response()
+        }
+        Err(err) => {
+            let status = match err {
+                relayer::Error::Disabled | relayer::Error::Unavailable(_) | relayer::Error::BudgetExhausted(_) => StatusCode::SERVICE_UNAVAILABLE,
+                relayer::Error::UnsupportedChain(_) | relayer::Error::Expired => StatusCode::BAD_REQUEST,
+                relayer::Error::StaleNonce => StatusCode::CONFLICT,
+                relayer::Error::InvalidSignature => StatusCode::UNAUTHORIZED,
+                relayer::Error::NotOwner => StatusCode::FORBIDDEN,
+                relayer::Error::QuotaExceeded => StatusCode::TOO_MANY_REQUESTS,
+                relayer::Error::Forwarder(_) | relayer::Error::Ownership(_) | relayer::Error::Submit(_) => StatusCode::BAD_GATEWAY,
+            };
+
+            (status, err.to_string()).into_response()
+        }
+    }
+}
+
+pub async fn get(State(relayer_service): State<Arc<RelayerService>>, Path(id): Path<String>) -> Response {
+    match relayer_service.job(&id).await {
+        Some(job) => Json(job).into_response(),
+        None => (StatusCode::NOT_FOUND, "Unknown relay").into_response(),
+    }
+}
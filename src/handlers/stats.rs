@@ -0,0 +1,37 @@
+use std::sync::Arc;
+
+use axum::extract::{Query, State};
+use axum::Json;
+use serde::Deserialize;
+
+use crate::response::stats::StatsResponse;
+use crate::services::avatar::AvatarService;
+use crate::supported_networks::SupportedNetworks;
+
+const DEFAULT_TOP: usize = 10;
+const MAX_TOP: usize = 100;
+const DEFAULT_DAYS: u64 = 30;
+const MAX_DAYS: u64 = 365;
+
+#[derive(Deserialize)]
+pub struct StatsParams {
+    top: Option<usize>,
+    days: Option<u64>
+}
+
+pub async fn get(State(avatar_service): State<Arc<AvatarService>>, Query(params): Query<StatsParams>) -> Json<StatsResponse> {
+    let top = params.top.unwrap_or(DEFAULT_TOP).clamp(1, MAX_TOP);
+    let days = params.days.unwrap_or(DEFAULT_DAYS).clamp(1, MAX_DAYS);
+
+    let mut response = StatsResponse::default();
+
+    // Counters are served as of the last background sync, `synced_to` tells how far they go
+    for network in SupportedNetworks::all() {
+        let verified_collections = avatar_service.cache.verified_collections.read().await;
+        let index = avatar_service.events.read(&network).await;
+
+        response.networks.insert(network.to_string().to_lowercase(), index.stats(verified_collections.get(&network), top, days));
+    }
+
+    Json(response)
+}
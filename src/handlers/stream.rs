@@ -0,0 +1,222 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use alloy::primitives::Address;
+use axum::extract::ws::{Message, WebSocket};
+use axum::extract::{Query, State, WebSocketUpgrade};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+
+use crate::models::event::AvatarSetEvent;
+use crate::response::stream::AvatarChangeResponse;
+use crate::services::avatar::AvatarService;
+use crate::services::stream::{Cursor, Feed, InvalidCursor, Position, Subscription, MAX_SUBSCRIBED_WALLETS};
+use crate::supported_networks::SupportedNetworks;
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+const STREAM_BUFFER: usize = 64;
+
+#[derive(Deserialize)]
+pub struct StreamParams {
+    // Comma separated wallet addresses
+    wallets: Option<String>,
+    network: Option<String>,
+    // Replays from this block of `network`, superseded by `cursor`
+    from_block: Option<u64>,
+    // Resumes per network, `network:block[:log_index],...`
+    cursor: Option<String>
+}
+
+impl StreamParams {
+    fn subscription(&self) -> Result<Subscription, Response> {
+        let wallets = self.wallets.as_deref().unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|wallet| !wallet.is_empty())
+            .map(str::parse::<Address>)
+            .collect::<Result<HashSet<_>, _>>()
+            .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid Ethereum address format").into_response())?;
+
+        if wallets.len() > MAX_SUBSCRIBED_WALLETS {
+            return Err((StatusCode::BAD_REQUEST, format!("At most {MAX_SUBSCRIBED_WALLETS} wallets can be followed")).into_response());
+        }
+
+        let networks = match &self.network {
+            Some(network) => vec![network.parse::<SupportedNetworks>().map_err(|_| (StatusCode::BAD_REQUEST, "Unknown network").into_response())?],
+            None => Vec::new()
+        };
+
+        Ok(Subscription { wallets, networks })
+    }
+
+    // An explicit cursor wins over `from_block`, which wins over the `Last-Event-ID` of a reconnect
+    fn cursor(&self, last_event_id: Option<&str>) -> Result<Cursor, Response> {
+        let invalid = |err: InvalidCursor| (StatusCode::BAD_REQUEST, err.to_string()).into_response();
+
+        if let Some(cursor) = &self.cursor {
+            return cursor.parse().map_err(invalid);
+        }
+
+        if let Some(from_block) = self.from_block {
+            let Some(network) = &self.network else {
+                return Err((StatusCode::BAD_REQUEST, "from_block requires a network, use cursor to resume several").into_response());
+            };
+
+            let network = network.parse::<SupportedNetworks>().map_err(|_| (StatusCode::BAD_REQUEST, "Unknown network").into_response())?;
+
+            return Ok(Cursor(HashMap::from([(network, Position::From(from_block))])));
+        }
+
+        last_event_id.map_or_else(|| Ok(Cursor::default()), |cursor| cursor.parse().map_err(invalid))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+enum ClientMessage {
+    Subscribe { wallets: Vec<Address> },
+    Unsubscribe { wallets: Vec<Address> }
+}
+
+async fn change(avatar_service: &AvatarService, network: SupportedNetworks, event: AvatarSetEvent, cursor: &Cursor) -> AvatarChangeResponse {
+    let avatar = avatar_service.resolve_event(&network, &event).await;
+
+    AvatarChangeResponse {
+        network: network.to_string().to_lowercase(),
+        event,
+        avatar,
+        cursor: cursor.to_string(),
+    }
+}
+
+/// Server-sent events for `AvatarSet` logs of the given wallets.
+///
+/// A `cursor` (or `from_block` of one `network`, or the `Last-Event-ID` of a reconnect) replays
+/// the indexed events it hasn't seen before following new ones. Every event carries the cursor
+/// to resume after it.
+pub async fn sse(State(avatar_service): State<Arc<AvatarService>>, headers: HeaderMap, Query(params): Query<StreamParams>) -> Response {
+    let subscription = match params.subscription() {
+        Ok(subscription) => subscription,
+        Err(response) => return response
+    };
+
+    if subscription.wallets.is_empty() {
+        return (StatusCode::BAD_REQUEST, "At least one wallet is required").into_response();
+    }
+
+    let last_event_id = headers.get("last-event-id").and_then(|value| value.to_str().ok());
+
+    let cursor = match params.cursor(last_event_id) {
+        Ok(cursor) => cursor,
+        Err(response) => return response
+    };
+
+    let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+
+    tokio::spawn(async move {
+        let mut feed = Feed::new(&avatar_service.events, subscription, &cursor).await;
+
+        loop {
+            tokio::select! {
+                next = feed.next() => {
+                    let Some((network, event, cursor)) = next else {
+                        return;
+                    };
+
+                    if sender.send(change(&avatar_service, network, event, &cursor).await).await.is_err() {
+                        return;
+                    }
+                }
+                () = sender.closed() => return
+            }
+        }
+    });
+
+    let stream = ReceiverStream::new(receiver)
+        .map(|change| Event::default().event("avatar").id(change.cursor.clone()).json_data(&change));
+
+    Sse::new(stream)
+        .keep_alive(KeepAlive::new().interval(HEARTBEAT_INTERVAL))
+        .into_response()
+}
+
+/// WebSocket variant of [`sse`], wallets can be added and removed with
+/// `{"action": "subscribe" | "unsubscribe", "wallets": [...]}` messages.
+pub async fn ws(State(avatar_service): State<Arc<AvatarService>>, Query(params): Query<StreamParams>, upgrade: WebSocketUpgrade) -> Response {
+    let subscription = match params.subscription() {
+        Ok(subscription) => subscription,
+        Err(response) => return response
+    };
+
+    let cursor = match params.cursor(None) {
+        Ok(cursor) => cursor,
+        Err(response) => return response
+    };
+
+    upgrade.on_upgrade(move |socket| follow(socket, avatar_service, subscription, cursor))
+}
+
+async fn send(socket: &mut WebSocket, change: &AvatarChangeResponse) -> Result<(), axum::Error> {
+    let payload = serde_json::to_string(change).map_err(axum::Error::new)?;
+
+    socket.send(Message::Text(payload)).await
+}
+
+async fn follow(mut socket: WebSocket, avatar_service: Arc<AvatarService>, subscription: Subscription, cursor: Cursor) {
+    let mut feed = Feed::new(&avatar_service.events, subscription, &cursor).await;
+
+    let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+
+    loop {
+        tokio::select! {
+            next = feed.next() => {
+                let Some((network, event, cursor)) = next else {
+                    return;
+                };
+
+                if send(&mut socket, &change(&avatar_service, network, event, &cursor).await).await.is_err() {
+                    return;
+                }
+            }
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    let reply = match serde_json::from_str::<ClientMessage>(&text) {
+                        Ok(ClientMessage::Subscribe { wallets }) if feed.subscription.wallets.len() + wallets.len() > MAX_SUBSCRIBED_WALLETS => {
+                            Some(format!("At most {MAX_SUBSCRIBED_WALLETS} wallets can be followed"))
+                        }
+                        Ok(ClientMessage::Subscribe { wallets }) => {
+                            feed.subscription.wallets.extend(wallets);
+                            None
+                        }
+                        Ok(ClientMessage::Unsubscribe { wallets }) => {
+                            for wallet in &wallets {
+                                feed.subscription.wallets.remove(wallet);
+                            }
+                            None
+                        }
+                        Err(err) => Some(format!("Invalid message: {err}"))
+                    };
+
+                    if let Some(reply) = reply {
+                        if socket.send(Message::Text(reply)).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                Some(Ok(Message::Close(_)) | Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+            _ = heartbeat.tick() => {
+                if socket.send(Message::Ping(Vec::new())).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
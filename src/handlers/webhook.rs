@@ -0,0 +1,98 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use alloy::primitives::Address;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::{Extension, Json};
+use axum::response::{IntoResponse, Response};
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::response::page::{PageParams, PageResponse};
+use crate::services::admin::AdminIdentity;
+use crate::services::webhook::{self, Webhook, WebhookFilter, WebhookService};
+use crate::supported_networks::SupportedNetworks;
+
+#[derive(Deserialize)]
+pub struct RegisterParams {
+    url: String,
+    #[serde(default)]
+    wallets: HashSet<Address>,
+    #[serde(default)]
+    collections: HashSet<Address>,
+    #[serde(default)]
+    networks: Vec<String>
+}
+
+#[derive(Serialize)]
+pub struct RegisteredWebhookResponse {
+    #[serde(flatten)]
+    webhook: Webhook,
+    // Key of the `X-Webhook-Signature` HMAC, not shown again
+    secret: String
+}
+
+fn error_response(err: &webhook::Error) -> Response {
+    let status = match err {
+        webhook::Error::InvalidUrl | webhook::Error::UnresolvableHost(_) | webhook::Error::NonPublicHost(_) => StatusCode::BAD_REQUEST,
+        webhook::Error::NotFound => StatusCode::NOT_FOUND,
+        webhook::Error::Persist(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+
+    (status, err.to_string()).into_response()
+}
+
+pub async fn register(State(webhook_service): State<Arc<WebhookService>>, Extension(identity): Extension<AdminIdentity>, Json(params): Json<RegisterParams>) -> Response {
+    let Ok(networks) = params.networks.iter().map(|network| network.parse::<SupportedNetworks>()).collect::<Result<HashSet<_>, _>>() else {
+        return (StatusCode::BAD_REQUEST, "Unknown network").into_response();
+    };
+
+    let filter = WebhookFilter {
+        wallets: params.wallets,
+        collections: params.collections,
+        networks,
+    };
+
+    match webhook_service.register(&identity.name, &params.url, filter).await {
+        Ok(webhook) => {
+            info!(target: "API", "Registered webhook {} for '{}': {}", webhook.id, identity.name, webhook.url);
+
+            let secret = webhook.secret.clone();
+
+            (StatusCode::CREATED, Json(RegisteredWebhookResponse { webhook, secret })).into_response()
+        }
+        Err(err) => error_response(&err)
+    }
+}
+
+pub async fn list(State(webhook_service): State<Arc<WebhookService>>, Extension(identity): Extension<AdminIdentity>) -> Json<Vec<Webhook>> {
+    Json(webhook_service.list(&identity.name).await)
+}
+
+pub async fn remove(State(webhook_service): State<Arc<WebhookService>>, Extension(identity): Extension<AdminIdentity>, Path(id): Path<String>) -> Response {
+    match webhook_service.remove(&identity.name, &id).await {
+        Ok(()) => {
+            info!(target: "API", "Removed webhook {id} of '{}'", identity.name);
+
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(err) => error_response(&err)
+    }
+}
+
+pub async fn deliveries(State(webhook_service): State<Arc<WebhookService>>, Extension(identity): Extension<AdminIdentity>, Path(id): Path<String>, Query(page): Query<PageParams>) -> Response {
+    if let Err(err) = webhook_service.get(&identity.name, &id).await {
+        return error_response(&err);
+    }
+
+    Json(PageResponse::new(webhook_service.deliveries(&id).await, &page)).into_response()
+}
+
+pub async fn dead_letters(State(webhook_service): State<Arc<WebhookService>>, Extension(identity): Extension<AdminIdentity>, Path(id): Path<String>, Query(page): Query<PageParams>) -> Response {
+    if let Err(err) = webhook_service.get(&identity.name, &id).await {
+        return error_response(&err);
+    }
+
+    Json(PageResponse::new(webhook_service.dead_letters(&id).await, &page)).into_response()
+}
@@ -1,22 +1,22 @@
 use std::collections::HashMap;
-use std::sync::{Arc, LazyLock};
+use std::sync::Arc;
 
 use alloy::primitives::Address;
-use axum::extract::State;
+use axum::extract::{Path, Query, State};
 use axum::http::StatusCode;
 use axum::Json;
 use axum::response::{IntoResponse, Response};
+use log::{error, info};
 use serde::Deserialize;
 
 use crate::models::avatar::AvatarCollection;
 use crate::response::error::AppResult;
+use crate::response::page::{PageParams, PageResponse};
+use crate::response::whitelist::{WhitelistCollectionResponse, WhitelistStatusResponse};
 use crate::services::avatar::AvatarService;
+use crate::services::whitelist;
 use crate::supported_networks::SupportedNetworks;
 
-static KEY: LazyLock<String> = LazyLock::new(|| {
-    std::env::var("KEY").expect("KEY not set")
-});
-
 #[allow(clippy::missing_errors_doc)]
 pub async fn get(State(avatar_service): State<Arc<AvatarService>>) -> AppResult<Json<HashMap<SupportedNetworks, HashMap<Address, AvatarCollection>>>> {
     let response = avatar_service.cache.verified_collections.read().await.clone();
@@ -25,16 +25,76 @@ pub async fn get(State(avatar_service): State<Arc<AvatarService>>) -> AppResult<
 }
 
 #[derive(Deserialize)]
-pub struct ReloadParams {
-    key: String
+pub struct SearchParams {
+    q: Option<String>
+}
+
+pub async fn get_network(State(avatar_service): State<Arc<AvatarService>>, Path(network): Path<String>, Query(params): Query<SearchParams>, Query(page): Query<PageParams>) -> Response {
+    let Ok(network) = network.parse::<SupportedNetworks>() else {
+        return (StatusCode::NOT_FOUND, "Unknown network").into_response();
+    };
+
+    let verified_collections = avatar_service.cache.verified_collections.read().await;
+
+    let collections = verified_collections.get(&network)
+        .map(|collections| whitelist::search(collections, params.q.as_deref()))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(contract, collection)| WhitelistCollectionResponse::new(&network, contract, collection));
+
+    Json(PageResponse::new(collections, &page)).into_response()
 }
 
-pub async fn reload(State(avatar_service): State<Arc<AvatarService>>, Json(params): Json<ReloadParams>) -> Response {
-    if params.key == *KEY {
-        avatar_service.reload_verified_collections().await;
+pub async fn get_collection(State(avatar_service): State<Arc<AvatarService>>, Path((network, contract)): Path<(String, String)>) -> Response {
+    let Ok(network) = network.parse::<SupportedNetworks>() else {
+        return (StatusCode::NOT_FOUND, "Unknown network").into_response();
+    };
 
-        (StatusCode::OK, "Reloaded whitelist").into_response()
-    } else {
-        (StatusCode::FORBIDDEN, "Wrong key").into_response()
+    let Ok(contract) = contract.parse::<Address>() else {
+        return (StatusCode::BAD_REQUEST, "Invalid Ethereum address format").into_response();
+    };
+
+    let verified_collections = avatar_service.cache.verified_collections.read().await;
+
+    match verified_collections.get(&network).and_then(|collections| collections.get(&contract)) {
+        Some(collection) => Json(WhitelistCollectionResponse::new(&network, &contract, collection)).into_response(),
+        None => (StatusCode::NOT_FOUND, "Collection not whitelisted").into_response(),
     }
-}
\ No newline at end of file
+}
+
+#[allow(clippy::missing_errors_doc)]
+pub async fn status(State(avatar_service): State<Arc<AvatarService>>) -> AppResult<Json<WhitelistStatusResponse>> {
+    let collections = avatar_service.cache.verified_collections.read().await
+        .values()
+        .map(HashMap::len)
+        .sum();
+
+    let response = WhitelistStatusResponse {
+        source: whitelist::WHITELIST_URL.clone(),
+        refresh_interval: whitelist::WHITELIST_REFRESH_INTERVAL.map(|interval| interval.as_secs()),
+        collections,
+        status: avatar_service.whitelist_status.read().await.clone(),
+    };
+
+    Ok(Json(response))
+}
+
+pub async fn reload(State(avatar_service): State<Arc<AvatarService>>) -> Response {
+    match avatar_service.reload_verified_collections(false).await {
+        Ok(response) => {
+            info!(target: "API", "Reloaded whitelist: {} added, {} removed, {} changed, {} warnings", response.added.len(), response.removed.len(), response.changed.len(), response.warnings.len());
+
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(err) => {
+            error!(target: "API", "Failed to reload whitelist: {err}");
+
+            let status = match err {
+                whitelist::Error::Fetch(_) | whitelist::Error::Status(_) => StatusCode::BAD_GATEWAY,
+                whitelist::Error::Decode(_) | whitelist::Error::Empty => StatusCode::UNPROCESSABLE_ENTITY,
+            };
+
+            (status, err.to_string()).into_response()
+        }
+    }
+}
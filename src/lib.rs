@@ -1,6 +1,8 @@
 pub mod handlers;
 pub mod extractors;
+pub mod middleware;
 pub mod services;
 pub mod models;
 pub mod response;
+pub mod state;
 pub mod supported_networks;
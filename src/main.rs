@@ -1,16 +1,30 @@
+use std::net::SocketAddr;
 use std::sync::{Arc, LazyLock};
 
 use axum::{
+    middleware,
+    routing::delete,
     routing::get,
     routing::post,
     Router,
 };
 use dotenv::dotenv;
-use log::info;
+use log::{error, info};
 use tower_http::cors::{Any, CorsLayer};
 
 use eas_api::handlers;
+use eas_api::middleware::admin::require_scope;
+use eas_api::middleware::rate_limit::rate_limit;
+use eas_api::services::admin::{AdminKeys, Scope};
 use eas_api::services::avatar::AvatarService;
+use eas_api::services::events::EVENT_POLL_INTERVAL;
+use eas_api::services::rate_limit::RateLimiter;
+use eas_api::services::relayer::RelayerService;
+use eas_api::services::rpc::{self, RPC_HEALTH_CHECK_INTERVAL};
+use eas_api::services::siwe::SessionService;
+use eas_api::services::webhook::{WebhookService, WebhookStores};
+use eas_api::services::whitelist::WHITELIST_REFRESH_INTERVAL;
+use eas_api::state::AppState;
 
 static BIND_ADDRESS: LazyLock<String> = LazyLock::new(|| {
     std::env::var("BIND_ADDRESS").expect("BIND_ADDRESS not set")
@@ -23,23 +37,106 @@ async fn main() {
     // initialize tracing
     tracing_subscriber::fmt::init();
 
-    let avatar_service = Arc::new(AvatarService::default());
+    let avatar_service = Arc::new(AvatarService::load().expect("Failed to restore persisted state"));
+
+    let admin_keys = Arc::new(AdminKeys::from_env().expect("Invalid ADMIN_API_KEYS"));
+
+    if admin_keys.is_empty() {
+        info!(target: "API", "ADMIN_API_KEYS not set, admin endpoints are disabled");
+    }
 
     // Load verified collections from GitHub: https://github.com/ethereum-avatar-service/eas-api-whitelist
-    avatar_service.reload_verified_collections().await;
+    if let Err(err) = avatar_service.reload_verified_collections(false).await {
+        error!(target: "API", "Failed to load whitelist: {err}");
+    }
+
+    if let Err(err) = avatar_service.moderation.reload().await {
+        error!(target: "API", "Failed to load blocklist: {err}");
+    }
+
+    if let Some(interval) = *WHITELIST_REFRESH_INTERVAL {
+        avatar_service.spawn_whitelist_refresh(interval);
+    }
+
+    if let Some(interval) = *EVENT_POLL_INTERVAL {
+        avatar_service.spawn_event_follower(interval);
+    }
+
+    if let Some(interval) = *RPC_HEALTH_CHECK_INTERVAL {
+        rpc::spawn_health_checks(interval);
+    }
+
+    let relayer_service = Arc::new(RelayerService::from_env().expect("Invalid RELAYER_PRIVATE_KEY"));
+
+    if let Some(address) = relayer_service.address() {
+        info!(target: "API", "Relaying transactions from {address}");
+    }
+
+    relayer_service.resume().await;
+
+    let webhook_service = Arc::new(WebhookService::load(WebhookStores::default()).expect("Failed to restore persisted webhooks"));
+    webhook_service.spawn_dispatcher(avatar_service.clone());
+
+    let rate_limiter = Arc::new(RateLimiter::from_env().expect("Invalid rate limit configuration"));
 
     let cors = CorsLayer::new().allow_origin(Any);
 
     let app = Router::new()
+        .route("/avatar/signed", post(handlers::avatar::set_signed))
+        .route("/avatar/stream", get(handlers::stream::sse))
+        .route("/avatar/stream/ws", get(handlers::stream::ws))
         .route("/avatar/:wallet_address", get(handlers::avatar::get))
+        .route("/avatar/:wallet_address/history", get(handlers::avatar::history))
+        .route("/avatar/:wallet_address/redirect", get(handlers::avatar::redirect))
+        .route("/avatar/:wallet_address/fallback.svg", get(handlers::avatar::fallback_svg))
         .route("/whitelist", get(handlers::whitelist::get))
-        .route("/whitelist/reload", post(handlers::whitelist::reload))
-        .with_state(avatar_service)
+        .route("/whitelist/status", get(handlers::whitelist::status))
+        .route("/whitelist/reload", post(handlers::whitelist::reload)
+            .route_layer(middleware::from_fn_with_state((admin_keys.clone(), Scope::WhitelistReload), require_scope)))
+        .route("/whitelist/:network", get(handlers::whitelist::get_network))
+        .route("/whitelist/:network/:contract", get(handlers::whitelist::get_collection))
+        .route("/moderation", get(handlers::moderation::get)
+            .route_layer(middleware::from_fn_with_state((admin_keys.clone(), Scope::ModerationRead), require_scope)))
+        .route("/moderation/block", post(handlers::moderation::block)
+            .route_layer(middleware::from_fn_with_state((admin_keys.clone(), Scope::ModerationWrite), require_scope)))
+        .route("/moderation/unblock", post(handlers::moderation::unblock)
+            .route_layer(middleware::from_fn_with_state((admin_keys.clone(), Scope::ModerationWrite), require_scope)))
+        .route("/moderation/reload", post(handlers::moderation::reload)
+            .route_layer(middleware::from_fn_with_state((admin_keys.clone(), Scope::ModerationWrite), require_scope)))
+        .route("/cache/purge", post(handlers::cache::purge)
+            .route_layer(middleware::from_fn_with_state((admin_keys.clone(), Scope::CachePurge), require_scope)))
+        .route("/collections/:network/:contract/users", get(handlers::collections::users))
+        .route("/tokens/:network/:contract/:token_id/users", get(handlers::collections::token_users))
+        .route("/nft/:network/:contract/:token_id", get(handlers::nft::get))
+        .route("/stats", get(handlers::stats::get))
+        .route("/webhooks", get(handlers::webhook::list)
+            .route_layer(middleware::from_fn_with_state((admin_keys.clone(), Scope::WebhooksRead), require_scope)))
+        .route("/webhooks", post(handlers::webhook::register)
+            .route_layer(middleware::from_fn_with_state((admin_keys.clone(), Scope::WebhooksWrite), require_scope)))
+        .route("/webhooks/:id", delete(handlers::webhook::remove)
+            .route_layer(middleware::from_fn_with_state((admin_keys.clone(), Scope::WebhooksWrite), require_scope)))
+        .route("/webhooks/:id/deliveries", get(handlers::webhook::deliveries)
+            .route_layer(middleware::from_fn_with_state((admin_keys.clone(), Scope::WebhooksRead), require_scope)))
+        .route("/webhooks/:id/dead-letters", get(handlers::webhook::dead_letters)
+            .route_layer(middleware::from_fn_with_state((admin_keys.clone(), Scope::WebhooksRead), require_scope)))
+        .route("/auth/nonce", get(handlers::auth::nonce))
+        .route("/auth/verify", post(handlers::auth::verify))
+        .route("/auth/session", get(handlers::auth::session))
+        .route("/auth/logout", post(handlers::auth::logout))
+        .route("/relay", post(handlers::relay::relay))
+        .route("/relay/:id", get(handlers::relay::get))
+        .with_state(AppState {
+            avatar_service,
+            session_service: Arc::new(SessionService::default()),
+            relayer_service,
+            webhook_service,
+        })
+        .layer(middleware::from_fn_with_state(rate_limiter, rate_limit))
         .layer(cors);
 
     let listener = tokio::net::TcpListener::bind(&*BIND_ADDRESS).await.unwrap();
 
     info!(target: "API", "Started on: {}", *BIND_ADDRESS);
     
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
 }
\ No newline at end of file
@@ -0,0 +1,47 @@
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use axum::http::StatusCode;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use log::{info, warn};
+
+use crate::services::admin::{AdminKeys, Scope};
+
+/// Authenticates `Authorization: Bearer <key>` against the admin keys and requires `scope`.
+///
+/// The matched identity is inserted as a request extension and every attempt is written to the
+/// `AUDIT` log target.
+pub async fn require_scope(State((admin_keys, scope)): State<(Arc<AdminKeys>, Scope)>, mut request: Request, next: Next) -> Response {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+
+    let maybe_token = request.headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+
+    let Some(identity) = maybe_token.and_then(|token| admin_keys.authenticate(token)) else {
+        warn!(target: "AUDIT", "Rejected unauthenticated {method} {path}");
+
+        return (StatusCode::UNAUTHORIZED, [(WWW_AUTHENTICATE, "Bearer")], "Missing or invalid API key").into_response();
+    };
+
+    if !identity.scopes.contains(&scope) {
+        warn!(target: "AUDIT", "Rejected {method} {path} by '{}': missing scope {scope}", identity.name);
+
+        return (StatusCode::FORBIDDEN, format!("Missing scope {scope}")).into_response();
+    }
+
+    let name = identity.name.clone();
+
+    request.extensions_mut().insert(identity);
+
+    let response = next.run(request).await;
+
+    info!(target: "AUDIT", "{method} {path} by '{name}' ({scope}): {}", response.status());
+
+    response
+}
@@ -0,0 +1,36 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::header::RETRY_AFTER;
+use axum::http::{HeaderName, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use log::warn;
+
+use crate::services::rate_limit::{Decision, RateLimiter};
+
+static X_API_KEY: HeaderName = HeaderName::from_static("x-api-key");
+static X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+
+/// Charges the request to its `X-Api-Key` or client IP, answering `429` with `Retry-After` once
+/// the bucket is empty.
+pub async fn rate_limit(State(rate_limiter): State<Arc<RateLimiter>>, ConnectInfo(peer): ConnectInfo<SocketAddr>, request: Request, next: Next) -> Response {
+    let header = |name: &HeaderName| request.headers().get(name).and_then(|value| value.to_str().ok());
+
+    let ip = rate_limiter.client_ip(peer.ip(), header(&X_FORWARDED_FOR));
+
+    let decision = rate_limiter.check(ip, header(&X_API_KEY).map(str::trim)).await;
+
+    match decision {
+        Decision::Allowed => next.run(request).await,
+        Decision::Limited(retry_after) => {
+            warn!(target: "API", "Rate limited {ip} on {}", request.uri().path());
+
+            let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+
+            (StatusCode::TOO_MANY_REQUESTS, [(RETRY_AFTER, seconds.to_string())], "Too many requests").into_response()
+        }
+        Decision::InvalidKey => (StatusCode::UNAUTHORIZED, "Invalid API key").into_response()
+    }
+}
@@ -1,5 +1,5 @@
 use alloy::primitives::{Address, U256};
-use serde::{Serialize, Serializer};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::services;
 
@@ -7,7 +7,10 @@ use crate::services;
 pub struct Avatar {
     pub token_address: Address,
     #[serde(serialize_with = "serialize_u256_as_decimal")]
-    pub token_id: U256
+    pub token_id: U256,
+    // CAIP-19 asset id, omitted when no avatar is set or the token standard is unknown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_id: Option<String>
 }
 
 impl From<services::rpc::AvatarService::Avatar> for Avatar {
@@ -15,39 +18,56 @@ impl From<services::rpc::AvatarService::Avatar> for Avatar {
         Self {
             token_address: value.tokenAddress,
             token_id: value.tokenId,
+            asset_id: None,
         }
     }
 }
 
-fn serialize_u256_as_decimal<S>(value: &U256, serializer: S) -> Result<S::Ok, S::Error>
+pub fn serialize_u256_as_decimal<S>(value: &U256, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
 {
     serializer.serialize_str(&value.to_string())
 }
 
+pub fn deserialize_u256_from_decimal<'de, D>(deserializer: D) -> Result<U256, D::Error>
+    where
+        D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+
+    U256::from_str_radix(&value, 10).map_err(serde::de::Error::custom)
+}
+
 #[allow(clippy::module_name_repetitions)]
 #[derive(Serialize)]
 pub struct AvatarInfo {
+    // CAIP-2 chain id and CAIP-10 id of the wallet
+    pub chain_id: String,
+    pub account_id: String,
     pub avatar: Avatar,
     pub owned: bool,
-    pub uri: String
+    pub uri: String,
+    pub moderated: bool,
+    pub source: AvatarSource,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirmed: Option<bool>
 }
 
-impl From<services::rpc::AvatarService::AvatarInfo> for AvatarInfo {
-    fn from(value: services::rpc::AvatarService::AvatarInfo) -> Self {
-        Self {
-            avatar: Avatar::from(value.avatar),
-            owned: value.owned,
-            uri: value.uri,
-        }
+impl AvatarInfo {
+    pub fn moderate(&mut self) {
+        self.moderated = true;
+        self.uri = String::new();
     }
 }
 
 #[allow(clippy::module_name_repetitions)]
-#[derive(Default, Serialize, Clone)]
+#[derive(Default, Serialize, Clone, Debug, PartialEq, Eq)]
 pub struct AvatarCollection {
     pub name: Option<String>,
+    pub symbol: Option<String>,
+    pub description: Option<String>,
+    pub image: Option<String>,
     pub author: Option<String>,
     pub website: Option<String>,
     pub opensea: Option<String>,
@@ -64,10 +84,39 @@ pub struct AvatarMetadata {
 #[allow(clippy::module_name_repetitions)]
 #[derive(Serialize)]
 pub struct AvatarInfoWithMetadata {
+    // CAIP-2 chain id and CAIP-10 id of the wallet
+    pub chain_id: String,
+    pub account_id: String,
     pub avatar: Avatar,
     pub owned: bool,
     pub uri: String,
-    pub avatar_metadata: AvatarMetadata
+    pub avatar_metadata: AvatarMetadata,
+    pub moderated: bool,
+    pub source: AvatarSource,
+    // Whether the avatar was set in a block past the network's confirmation depth, omitted while
+    // the event index has not synced the network
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirmed: Option<bool>
+}
+
+impl AvatarInfoWithMetadata {
+    // Hides everything that could point to the blocked image
+    pub fn moderate(&mut self) {
+        self.moderated = true;
+        self.uri = String::new();
+        self.avatar_metadata.image = None;
+    }
+}
+
+#[allow(clippy::module_name_repetitions)]
+#[derive(Serialize, Debug, Clone, Copy, Eq, PartialEq)]
+pub enum AvatarSource {
+    // Set through `setAvatar` on the AvatarService contract
+    #[serde(rename = "onchain")]
+    Onchain,
+    // Set through an EIP-712 signed request stored by this API
+    #[serde(rename = "signed")]
+    Signed
 }
 
 #[allow(clippy::module_name_repetitions)]
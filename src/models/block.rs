@@ -0,0 +1,10 @@
+use alloy::primitives::B256;
+use serde::Serialize;
+
+/// Block a lookup was made at.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockRef {
+    pub number: u64,
+    pub hash: B256,
+    pub timestamp: u64
+}
@@ -0,0 +1,21 @@
+use alloy::primitives::{Address, B256, U256};
+use serde::Serialize;
+
+use crate::models::avatar::serialize_u256_as_decimal;
+
+/// An `AvatarSet` log emitted by the AvatarService contract.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct AvatarSetEvent {
+    pub wallet: Address,
+    pub token_address: Address,
+    #[serde(serialize_with = "serialize_u256_as_decimal")]
+    pub token_id: U256,
+    pub block_number: u64,
+    pub block_hash: B256,
+    pub transaction_hash: B256,
+    pub log_index: u64,
+    pub timestamp: Option<u64>,
+    // Whether the block is deep enough to be final, filled in when the event is served
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirmed: Option<bool>
+}
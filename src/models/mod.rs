@@ -1,3 +1,6 @@
 pub mod avatar;
+pub mod block;
+pub mod event;
+pub mod moderation;
 pub mod nft;
-pub mod whitelist;
\ No newline at end of file
+pub mod whitelist;
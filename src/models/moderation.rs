@@ -0,0 +1,15 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct BlockedContract {
+    pub contract: String,
+    // Blocks the whole contract when empty
+    #[serde(default)]
+    pub token_ids: Vec<String>,
+    pub reason: Option<String>
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct Blocklist(pub HashMap<String, Vec<BlockedContract>>);
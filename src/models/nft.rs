@@ -1,6 +1,59 @@
-use serde::Deserialize;
+use alloy::primitives::{Address, U256};
+use serde::{Deserialize, Serialize};
 
-#[derive(Default, Deserialize, Clone)]
+use crate::models::avatar::{serialize_u256_as_decimal, AvatarCollection};
+
+#[derive(Default, Deserialize, Serialize, Clone)]
 pub struct NftMetadata {
     pub image: Option<String>
-}
\ No newline at end of file
+}
+
+#[derive(Default, Deserialize, Clone, Debug)]
+pub struct ContractMetadata {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub image: Option<String>,
+    pub external_link: Option<String>
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenStandard {
+    #[serde(rename = "erc721")]
+    Erc721,
+    #[serde(rename = "erc1155")]
+    Erc1155
+}
+
+impl TokenStandard {
+    /// Asset namespace used in CAIP-19 ids.
+    pub fn caip19_namespace(&self) -> &'static str {
+        match self {
+            TokenStandard::Erc721 => "erc721",
+            TokenStandard::Erc1155 => "erc1155",
+        }
+    }
+}
+
+/// A token resolved independently of any wallet.
+#[derive(Serialize)]
+pub struct NftInfo {
+    pub chain_id: String,
+    pub asset_id: String,
+    pub contract: Address,
+    #[serde(serialize_with = "serialize_u256_as_decimal")]
+    pub token_id: U256,
+    pub standard: TokenStandard,
+    pub token_uri: String,
+    pub metadata: NftMetadata,
+    pub collection: Option<AvatarCollection>,
+    pub moderated: bool
+}
+
+impl NftInfo {
+    // Hides everything that could point to the blocked image
+    pub fn moderate(&mut self) {
+        self.moderated = true;
+        self.token_uri = String::new();
+        self.metadata.image = None;
+    }
+}
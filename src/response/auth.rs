@@ -0,0 +1,15 @@
+use serde::Serialize;
+
+use crate::services::siwe::Session;
+
+#[derive(Serialize)]
+pub struct NonceResponse {
+    pub nonce: String
+}
+
+#[derive(Serialize)]
+pub struct SessionResponse {
+    pub token: String,
+    #[serde(flatten)]
+    pub session: Session
+}
@@ -2,13 +2,44 @@ use std::collections::HashMap;
 use serde::Serialize;
 
 use crate::models::avatar::{AvatarInfo, AvatarInfoWithMetadata, AvatarType};
+use crate::models::block::BlockRef;
+use crate::services::primary::PrimaryReason;
 
 #[derive(Default, Serialize)]
 pub struct AvatarInfoResponse {
-    pub networks: HashMap<String, HashMap<AvatarType, Option<AvatarInfo>>>
+    pub networks: HashMap<String, HashMap<AvatarType, Option<AvatarInfo>>>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub blocks: HashMap<String, BlockRef>,
+    // Set when no network has an avatar, clients should show `fallback_image`
+    pub fallback: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback_image: Option<String>
 }
 
 #[derive(Default, Serialize)]
 pub struct AvatarInfoWithMetadataResponse {
-    pub networks: HashMap<String, HashMap<AvatarType, Option<AvatarInfoWithMetadata>>>
+    pub networks: HashMap<String, HashMap<AvatarType, Option<AvatarInfoWithMetadata>>>,
+    // Blocks used per network for historical lookups
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub blocks: HashMap<String, BlockRef>,
+    // Set when no network has an avatar, clients should show `fallback_image`
+    pub fallback: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback_image: Option<String>
+}
+
+/// The single avatar picked by `?resolve=primary`, all fields are empty without any avatar.
+#[derive(Default, Serialize)]
+pub struct PrimaryAvatarResponse {
+    pub network: Option<String>,
+    pub avatar: Option<AvatarInfoWithMetadata>,
+    pub reason: Option<PrimaryReason>,
+    // Block timestamp the avatar was set at, when indexed
+    pub set_at: Option<u64>,
+    // Block used for a historical lookup
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block: Option<BlockRef>,
+    pub fallback: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback_image: Option<String>
 }
@@ -0,0 +1,91 @@
+use serde_json::{Map, Value};
+
+/// Avatar fields selected with `fields=`, as comma separated dotted paths like
+/// `avatar.token_id,avatar_metadata.image`.
+pub struct Fields(Vec<Vec<String>>);
+
+impl Fields {
+    pub fn new(fields: &str) -> Self {
+        let paths = fields.split(',')
+            .map(str::trim)
+            .filter(|field| !field.is_empty())
+            .map(|field| field.split('.').map(ToString::to_string).collect())
+            .collect();
+
+        Self(paths)
+    }
+
+    /// Narrows every avatar of a serialized avatar response down to the selected fields.
+    ///
+    /// Avatars sit under `networks.<network>.<type>` or, for a primary avatar, under `avatar`.
+    /// Paths that don't exist are left out.
+    pub fn apply(&self, response: &mut Value) {
+        if self.0.is_empty() {
+            return;
+        }
+
+        if let Some(networks) = response.get_mut("networks").and_then(Value::as_object_mut) {
+            for avatars in networks.values_mut().filter_map(Value::as_object_mut) {
+                for avatar in avatars.values_mut().filter(|avatar| avatar.is_object()) {
+                    *avatar = self.project(avatar);
+                }
+            }
+        }
+
+        if let Some(avatar) = response.get_mut("avatar").filter(|avatar| avatar.is_object()) {
+            *avatar = self.project(avatar);
+        }
+    }
+
+    fn project(&self, value: &Value) -> Value {
+        let mut projected = Map::new();
+
+        for path in &self.0 {
+            if let Some(selected) = path.iter().try_fold(value, |value, key| value.get(key)) {
+                insert(&mut projected, path, selected.clone());
+            }
+        }
+
+        Value::Object(projected)
+    }
+}
+
+fn insert(target: &mut Map<String, Value>, path: &[String], value: Value) {
+    match path {
+        [] => {}
+        [key] => {
+            target.insert(key.clone(), value);
+        }
+        [key, rest @ ..] => {
+            if let Value::Object(child) = target.entry(key.clone()).or_insert_with(|| Value::Object(Map::new())) {
+                insert(child, rest, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::response::fields::Fields;
+
+    #[test]
+    fn test_apply_keeps_selected_paths() {
+        let mut response = json!({
+            "networks": {
+                "ethereum": { "flat": { "avatar": { "token_address": "0x01", "token_id": "1" }, "owned": true, "uri": "ipfs://a" } },
+                "base": { "flat": null }
+            }
+        });
+
+        Fields::new("avatar.token_id, owned, missing.path").apply(&mut response);
+
+        assert_eq!(response, json!({
+            "networks": {
+                "ethereum": { "flat": { "avatar": { "token_id": "1" }, "owned": true } },
+                "base": { "flat": null }
+            }
+        }));
+    }
+}
@@ -1,2 +1,9 @@
 pub mod error;
-pub mod avatar;
\ No newline at end of file
+pub mod auth;
+pub mod avatar;
+pub mod fields;
+pub mod moderation;
+pub mod page;
+pub mod stats;
+pub mod stream;
+pub mod whitelist;
@@ -0,0 +1,26 @@
+use alloy::primitives::Address;
+use serde::Serialize;
+
+use crate::services::moderation::BlockedEntry;
+use crate::supported_networks::SupportedNetworks;
+
+#[derive(Serialize)]
+pub struct BlockedContractResponse {
+    pub network: SupportedNetworks,
+    pub contract: Address,
+    pub token_ids: Option<Vec<String>>,
+    pub reason: Option<String>,
+    pub manual: bool
+}
+
+impl BlockedContractResponse {
+    pub fn new(network: &SupportedNetworks, contract: &Address, entry: &BlockedEntry, manual: bool) -> Self {
+        Self {
+            network: network.clone(),
+            contract: *contract,
+            token_ids: entry.token_ids.as_ref().map(|token_ids| token_ids.iter().map(ToString::to_string).collect()),
+            reason: entry.reason.clone(),
+            manual,
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 200;
+
+#[derive(Deserialize, Default)]
+pub struct PageParams {
+    pub offset: Option<usize>,
+    pub limit: Option<usize>
+}
+
+impl PageParams {
+    pub fn offset(&self) -> usize {
+        self.offset.unwrap_or(0)
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+}
+
+#[derive(Serialize)]
+pub struct PageResponse<T> {
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+    pub items: Vec<T>
+}
+
+impl<T> PageResponse<T> {
+    pub fn new(items: impl IntoIterator<Item=T>, params: &PageParams) -> Self {
+        let items: Vec<T> = items.into_iter().collect();
+        let (offset, limit) = (params.offset(), params.limit());
+
+        Self {
+            total: items.len(),
+            offset,
+            limit,
+            items: items.into_iter().skip(offset).take(limit).collect(),
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use std::collections::{BTreeMap, HashMap};
+
+use alloy::primitives::Address;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct CollectionUsageResponse {
+    pub contract: Address,
+    pub name: Option<String>,
+    pub verified: bool,
+    pub users: usize
+}
+
+#[derive(Serialize)]
+pub struct NetworkStatsResponse {
+    pub synced_to: Option<u64>,
+    pub avatar_sets: usize,
+    pub wallets_with_avatar: usize,
+    pub verified_avatars: usize,
+    pub unverified_avatars: usize,
+    // Share of current avatars from whitelisted collections, absent without avatars
+    pub verified_ratio: Option<f64>,
+    pub top_collections: Vec<CollectionUsageResponse>,
+    // Keyed by UTC date, days without avatar sets are left out
+    pub daily_avatar_sets: BTreeMap<String, usize>
+}
+
+#[derive(Default, Serialize)]
+pub struct StatsResponse {
+    pub networks: HashMap<String, NetworkStatsResponse>
+}
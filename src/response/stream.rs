@@ -0,0 +1,14 @@
+use serde::Serialize;
+
+use crate::models::avatar::AvatarInfoWithMetadata;
+use crate::models::event::AvatarSetEvent;
+
+#[derive(Serialize)]
+pub struct AvatarChangeResponse {
+    pub network: String,
+    pub event: AvatarSetEvent,
+    // Resolved at the event's block, absent when resolution failed
+    pub avatar: Option<AvatarInfoWithMetadata>,
+    // Resumes the stream after this event, see `services::stream::Cursor`
+    pub cursor: String
+}
@@ -0,0 +1,41 @@
+use alloy::primitives::Address;
+use serde::Serialize;
+
+use crate::models::avatar::AvatarCollection;
+use crate::services::whitelist::WhitelistStatus;
+use crate::supported_networks::SupportedNetworks;
+
+#[derive(Serialize)]
+pub struct WhitelistCollectionResponse {
+    pub network: SupportedNetworks,
+    pub contract: Address,
+    pub collection: AvatarCollection
+}
+
+impl WhitelistCollectionResponse {
+    pub fn new(network: &SupportedNetworks, contract: &Address, collection: &AvatarCollection) -> Self {
+        Self {
+            network: network.clone(),
+            contract: *contract,
+            collection: collection.clone(),
+        }
+    }
+}
+
+#[derive(Default, Serialize)]
+pub struct WhitelistReloadResponse {
+    pub modified: bool,
+    pub added: Vec<WhitelistCollectionResponse>,
+    pub removed: Vec<WhitelistCollectionResponse>,
+    pub changed: Vec<WhitelistCollectionResponse>,
+    pub warnings: Vec<String>
+}
+
+#[derive(Serialize)]
+pub struct WhitelistStatusResponse {
+    pub source: String,
+    pub refresh_interval: Option<u64>,
+    pub collections: usize,
+    #[serde(flatten)]
+    pub status: WhitelistStatus
+}
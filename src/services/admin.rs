@@ -0,0 +1,175 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use alloy::primitives::hex;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scope {
+    WhitelistReload,
+    ModerationRead,
+    ModerationWrite,
+    CachePurge,
+    WebhooksRead,
+    WebhooksWrite
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::WhitelistReload => "whitelist:reload",
+            Scope::ModerationRead => "moderation:read",
+            Scope::ModerationWrite => "moderation:write",
+            Scope::CachePurge => "cache:purge",
+            Scope::WebhooksRead => "webhooks:read",
+            Scope::WebhooksWrite => "webhooks:write",
+        }
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Unknown scope: {0}")]
+    UnknownScope(String),
+    #[error("Malformed admin key entry: {0}")]
+    MalformedEntry(String),
+    #[error("Admin key hash for '{0}' must be a hex encoded SHA-256 digest")]
+    InvalidHash(String)
+}
+
+impl FromStr for Scope {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "whitelist:reload" => Ok(Scope::WhitelistReload),
+            "moderation:read" => Ok(Scope::ModerationRead),
+            "moderation:write" => Ok(Scope::ModerationWrite),
+            "cache:purge" => Ok(Scope::CachePurge),
+            "webhooks:read" => Ok(Scope::WebhooksRead),
+            "webhooks:write" => Ok(Scope::WebhooksWrite),
+            _ => Err(Error::UnknownScope(s.to_string()))
+        }
+    }
+}
+
+pub struct AdminKey {
+    pub name: String,
+    hash: [u8; 32],
+    pub scopes: HashSet<Scope>
+}
+
+#[derive(Clone, Debug)]
+pub struct AdminIdentity {
+    pub name: String,
+    pub scopes: HashSet<Scope>
+}
+
+/// Named admin API keys, only their SHA-256 digests are kept.
+#[derive(Default)]
+pub struct AdminKeys(Vec<AdminKey>);
+
+impl AdminKeys {
+    /// Reads `ADMIN_API_KEYS`, formatted as `name:sha256hex:scope,scope;name:sha256hex:scope`.
+    ///
+    /// A digest can be produced with `printf '%s' "$KEY" | sha256sum`. Without the variable no
+    /// admin request is accepted.
+    #[allow(clippy::missing_errors_doc)]
+    pub fn from_env() -> Result<Self, Error> {
+        std::env::var("ADMIN_API_KEYS").map_or_else(|_| Ok(Self::default()), |value| value.parse())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Looks up the key matching `token`.
+    ///
+    /// Every configured digest is compared in constant time, so the timing reveals neither the
+    /// token nor which key it matched.
+    pub fn authenticate(&self, token: &str) -> Option<AdminIdentity> {
+        let hash: [u8; 32] = Sha256::digest(token.as_bytes()).into();
+
+        let mut identity = None;
+
+        for key in &self.0 {
+            if bool::from(key.hash[..].ct_eq(&hash[..])) {
+                identity = Some(AdminIdentity {
+                    name: key.name.clone(),
+                    scopes: key.scopes.clone(),
+                });
+            }
+        }
+
+        identity
+    }
+}
+
+impl FromStr for AdminKeys {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut keys = Vec::new();
+
+        for entry in s.split(';').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let mut parts = entry.splitn(3, ':');
+
+            let (Some(name), Some(hash), scopes) = (parts.next(), parts.next(), parts.next()) else {
+                return Err(Error::MalformedEntry(entry.to_string()));
+            };
+
+            let hash = hex::decode(hash).ok()
+                .and_then(|hash| <[u8; 32]>::try_from(hash).ok())
+                .ok_or_else(|| Error::InvalidHash(name.to_string()))?;
+
+            let scopes = scopes.unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|scope| !scope.is_empty())
+                .map(str::parse)
+                .collect::<Result<HashSet<Scope>, _>>()?;
+
+            keys.push(AdminKey { name: name.to_string(), hash, scopes });
+        }
+
+        Ok(Self(keys))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::services::admin::{AdminKeys, Scope};
+
+    // sha256("secret")
+    const SECRET_HASH: &str = "2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b";
+
+    #[test]
+    fn test_authenticate() {
+        let keys: AdminKeys = format!("ops:{SECRET_HASH}:whitelist:reload,cache:purge").parse().unwrap();
+
+        let identity = keys.authenticate("secret").unwrap();
+
+        assert_eq!(identity.name, "ops");
+        assert!(identity.scopes.contains(&Scope::WhitelistReload));
+        assert!(identity.scopes.contains(&Scope::CachePurge));
+        assert!(!identity.scopes.contains(&Scope::ModerationWrite));
+
+        assert!(keys.authenticate("wrong").is_none());
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_entries() {
+        assert!("ops:not-hex:cache:purge".parse::<AdminKeys>().is_err());
+        assert!(format!("ops:{SECRET_HASH}:unknown").parse::<AdminKeys>().is_err());
+        assert!("ops".parse::<AdminKeys>().is_err());
+    }
+}
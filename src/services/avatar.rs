@@ -1,98 +1,497 @@
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
 
 use alloy::primitives::{Address, U256};
+use alloy::rpc::types::eth::BlockId;
+use log::{error, info, warn};
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
 
-use crate::models::avatar::{AvatarCollection, AvatarType};
-use crate::models::nft::NftMetadata;
-use crate::models::whitelist;
-use crate::response::avatar::AvatarInfoWithMetadataResponse;
-use crate::services::rpc;
+use crate::models::avatar::{Avatar, AvatarCollection, AvatarInfo, AvatarInfoWithMetadata, AvatarType};
+use crate::models::block::BlockRef;
+use crate::models::event::AvatarSetEvent;
+use crate::models::nft::{NftInfo, NftMetadata, TokenStandard};
+use crate::response::avatar::{AvatarInfoResponse, AvatarInfoWithMetadataResponse, PrimaryAvatarResponse};
+use crate::response::whitelist::WhitelistReloadResponse;
+use crate::services::events::EventIndex;
+use crate::services::moderation::ModerationService;
+use crate::services::primary::{Candidate, PrimaryPolicy, PRIMARY_POLICY};
+use crate::services::signed_avatar::{SignedAvatar, SignedAvatarService};
+use crate::services::rpc::BlockSelector;
+use crate::services::store::Store;
+use crate::services::{blockies, rpc, whitelist};
 use crate::supported_networks::SupportedNetworks;
 
+// Image redirected to for wallets without an avatar
+pub static DEFAULT_AVATAR_URL: LazyLock<Option<String>> = LazyLock::new(|| {
+    std::env::var("DEFAULT_AVATAR_URL").ok().filter(|url| !url.is_empty())
+});
+
 pub type VerifiedCollections = HashMap<SupportedNetworks, HashMap<Address, AvatarCollection>>;
 pub type IpfsCache = HashMap<String, NftMetadata>;
 pub type TokenUriCache = HashMap<SupportedNetworks, HashMap<(Address, U256), String>>;
+pub type CollectionCache = HashMap<SupportedNetworks, HashMap<Address, Option<AvatarCollection>>>;
+pub type TokenStandardCache = HashMap<SupportedNetworks, HashMap<Address, TokenStandard>>;
+// Blocks a historical lookup runs at, per network
+pub type Blocks = HashMap<SupportedNetworks, BlockRef>;
+// Avatars looked up per network key
+type NetworkAvatars<T> = HashMap<String, HashMap<AvatarType, Option<T>>>;
+
+/// The two forms of a looked up avatar, with and without token metadata.
+#[async_trait::async_trait]
+trait AvatarLookup: Sized + Send {
+    async fn fetch(provider: &rpc::Client, address: &Address, block: BlockId, cache: &Arc<AvatarServiceCache>) -> eyre::Result<Self>;
+
+    async fn fetch_signed(provider: &rpc::Client, address: &Address, signed_avatar: &SignedAvatar, cache: &Arc<AvatarServiceCache>) -> eyre::Result<Self>;
+
+    fn avatar(&self) -> &Avatar;
+
+    fn set_confirmed(&mut self, confirmed: Option<bool>);
+
+    fn moderate(&mut self);
+
+    fn is_moderated(&self) -> bool;
+}
+
+#[async_trait::async_trait]
+impl AvatarLookup for AvatarInfo {
+    async fn fetch(provider: &rpc::Client, address: &Address, block: BlockId, cache: &Arc<AvatarServiceCache>) -> eyre::Result<Self> {
+        provider.get_avatar_info(address, block, cache).await
+    }
+
+    async fn fetch_signed(provider: &rpc::Client, address: &Address, signed_avatar: &SignedAvatar, cache: &Arc<AvatarServiceCache>) -> eyre::Result<Self> {
+        provider.get_signed_avatar_info(address, signed_avatar, cache).await
+    }
+
+    fn avatar(&self) -> &Avatar {
+        &self.avatar
+    }
+
+    fn set_confirmed(&mut self, confirmed: Option<bool>) {
+        self.confirmed = confirmed;
+    }
+
+    fn moderate(&mut self) {
+        AvatarInfo::moderate(self);
+    }
+
+    fn is_moderated(&self) -> bool {
+        self.moderated
+    }
+}
+
+#[async_trait::async_trait]
+impl AvatarLookup for AvatarInfoWithMetadata {
+    async fn fetch(provider: &rpc::Client, address: &Address, block: BlockId, cache: &Arc<AvatarServiceCache>) -> eyre::Result<Self> {
+        provider.get_avatar_info_with_metadata(address, block, cache.clone()).await
+    }
+
+    async fn fetch_signed(provider: &rpc::Client, address: &Address, signed_avatar: &SignedAvatar, cache: &Arc<AvatarServiceCache>) -> eyre::Result<Self> {
+        provider.get_signed_avatar_info_with_metadata(address, signed_avatar, cache.clone()).await
+    }
+
+    fn avatar(&self) -> &Avatar {
+        &self.avatar
+    }
+
+    fn set_confirmed(&mut self, confirmed: Option<bool>) {
+        self.confirmed = confirmed;
+    }
+
+    fn moderate(&mut self) {
+        AvatarInfoWithMetadata::moderate(self);
+    }
+
+    fn is_moderated(&self) -> bool {
+        self.moderated
+    }
+}
+
+// Whether no network has an avatar that can be shown, in which case clients show the fallback
+// image. Moderated avatars have their image hidden.
+fn is_fallback<T: AvatarLookup>(avatars: &NetworkAvatars<T>) -> bool {
+    !avatars.values()
+        .flat_map(HashMap::values)
+        .flatten()
+        .any(|avatar_info| avatar_info.avatar().token_address != Address::ZERO && !avatar_info.is_moderated())
+}
 
 #[allow(clippy::module_name_repetitions)]
 #[derive(Default)]
 pub struct AvatarServiceCache {
     pub verified_collections: RwLock<VerifiedCollections>,
     pub ipfs: Arc<RwLock<IpfsCache>>,
-    pub token_uris: Arc<RwLock<TokenUriCache>>
+    pub token_uris: Arc<RwLock<TokenUriCache>>,
+    pub collections: Arc<RwLock<CollectionCache>>,
+    pub token_standards: Arc<RwLock<TokenStandardCache>>
 }
 
 #[allow(clippy::module_name_repetitions)]
 #[derive(Default)]
 pub struct AvatarService {
-    pub cache: Arc<AvatarServiceCache>
+    pub cache: Arc<AvatarServiceCache>,
+    pub whitelist_status: RwLock<whitelist::WhitelistStatus>,
+    pub moderation: ModerationService,
+    pub signed_avatars: SignedAvatarService,
+    pub events: EventIndex
 }
 
 impl AvatarService {
-    pub async fn reload_verified_collections(&self) {
-        let result = reqwest::get("https://raw.githubusercontent.com/ethereum-avatar-service/eas-api-whitelist/main/collections.json").await;
-
-        let mut verified_collections = self.cache.verified_collections.write().await;
-
-        if let Ok(response) = result {
-            match response.json::<whitelist::Collections>().await {
-                Ok(collections) => {
-                    for (network, network_collections) in collections.0 {
-                        for collection in network_collections {
-                            if let Ok(address) = collection.contract.parse::<Address>() {
-                                let chain = match network.to_lowercase().as_str() {
-                                    "mainnet" | "ethereum" => SupportedNetworks::Ethereum,
-                                    "sepolia" => SupportedNetworks::Sepolia,
-                                    "polygon" => SupportedNetworks::Polygon,
-                                    "base" => SupportedNetworks::Base,
-                                    _ => { continue; }
-                                };
-
-                                let entry = verified_collections.entry(chain).or_default();
-
-                                entry.insert(address, AvatarCollection {
-                                    name: Some(collection.name.clone()),
-                                    author: Some(collection.author.clone()),
-                                    website: Some(collection.website.clone()),
-                                    opensea: collection.opensea.clone(),
-                                    verified: true,
-                                });
-                            }
-                        }
+    /// Resolves `selector` on every network up front, so an unknown block fails the whole lookup
+    /// instead of leaving its network empty.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn resolve_blocks(&self, networks: &[SupportedNetworks], selector: BlockSelector) -> eyre::Result<Blocks> {
+        let mut blocks = Blocks::new();
+
+        for network in networks {
+            blocks.insert(network.clone(), rpc::client(network).resolve_block(selector).await?);
+        }
+
+        Ok(blocks)
+    }
+
+    /// The service with the state persisted in `DATA_DIR` restored.
+    #[allow(clippy::missing_errors_doc)]
+    pub fn load() -> eyre::Result<Self> {
+        Ok(Self {
+            moderation: ModerationService::load(Store::new("moderation.json"))?,
+            signed_avatars: SignedAvatarService::load(Store::new("signed_avatars.json"))?,
+            ..Default::default()
+        })
+    }
+
+    /// Fetches and validates the whitelist without holding the lock, then swaps it in atomically.
+    ///
+    /// A `conditional` reload sends the validators of the last applied whitelist and leaves the
+    /// collections untouched when the source reports them as not modified.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn reload_verified_collections(&self, conditional: bool) -> Result<WhitelistReloadResponse, whitelist::Error> {
+        self.reload_verified_collections_from(&whitelist::WHITELIST_URL, conditional).await
+    }
+
+    pub(crate) async fn reload_verified_collections_from(&self, url: &str, conditional: bool) -> Result<WhitelistReloadResponse, whitelist::Error> {
+        let validators = if conditional {
+            Some(self.whitelist_status.read().await.validators.clone())
+        } else {
+            None
+        };
+
+        let fetched = whitelist::fetch(url, validators.as_ref()).await;
+
+        let mut status = self.whitelist_status.write().await;
+
+        match fetched {
+            Ok(whitelist::Fetched::NotModified) => {
+                status.record_success(None);
+
+                Ok(WhitelistReloadResponse::default())
+            }
+            Ok(whitelist::Fetched::Modified { verified_collections, warnings, validators }) => {
+                let mut current = self.cache.verified_collections.write().await;
+
+                let mut response = whitelist::diff(&current, &verified_collections);
+                response.modified = true;
+                response.warnings = warnings;
+
+                *current = verified_collections;
+
+                status.record_success(Some(validators));
+
+                Ok(response)
+            }
+            Err(err) => {
+                status.record_failure(&err);
+
+                Err(err)
+            }
+        }
+    }
+
+    pub async fn purge_caches(&self) {
+        self.cache.ipfs.write().await.clear();
+        self.cache.token_uris.write().await.clear();
+        self.cache.collections.write().await.clear();
+        self.cache.token_standards.write().await.clear();
+    }
+
+    pub fn spawn_whitelist_refresh(self: &Arc<Self>, period: Duration) -> JoinHandle<()> {
+        let avatar_service = self.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            // The first tick completes immediately, the whitelist was just loaded at startup
+            interval.tick().await;
+
+            loop {
+                interval.tick().await;
+
+                match avatar_service.reload_verified_collections(true).await {
+                    Ok(response) if response.modified => {
+                        info!(target: "API", "Refreshed whitelist: {} added, {} removed, {} changed, {} warnings", response.added.len(), response.removed.len(), response.changed.len(), response.warnings.len());
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        error!(target: "API", "Failed to refresh whitelist: {err}");
                     }
                 }
-                Err(err) => {
-                    println!("{err}");
+            }
+        })
+    }
+
+    /// Looks the avatar up without resolving token metadata, only signed avatars need a `tokenURI`
+    /// call since the contract doesn't return their URI.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn get_info(&self, address: &Address, networks: impl IntoIterator<Item=SupportedNetworks>, blocks: Option<&Blocks>) -> eyre::Result<AvatarInfoResponse> {
+        let (networks, blocks) = self.lookup_networks::<AvatarInfo>(address, networks, blocks).await;
+        let fallback = is_fallback(&networks);
+
+        Ok(AvatarInfoResponse {
+            networks,
+            blocks,
+            fallback,
+            fallback_image: fallback.then(|| blockies::path(address)),
+        })
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn get_info_with_metadata(&self, address: &Address, networks: impl IntoIterator<Item=SupportedNetworks>, blocks: Option<&Blocks>) -> eyre::Result<AvatarInfoWithMetadataResponse> {
+        let (networks, blocks) = self.lookup_networks::<AvatarInfoWithMetadata>(address, networks, blocks).await;
+        let fallback = is_fallback(&networks);
+
+        Ok(AvatarInfoWithMetadataResponse {
+            networks,
+            blocks,
+            fallback,
+            fallback_image: fallback.then(|| blockies::path(address)),
+        })
+    }
+
+    /// Looks the avatar up on every network, along with the blocks of a historical lookup.
+    async fn lookup_networks<T: AvatarLookup>(&self, address: &Address, networks: impl IntoIterator<Item=SupportedNetworks>, blocks: Option<&Blocks>) -> (NetworkAvatars<T>, HashMap<String, BlockRef>) {
+        let mut avatars = NetworkAvatars::new();
+        let mut used_blocks = HashMap::new();
+
+        for network in networks {
+            let key = network.to_string().to_lowercase();
+            let block = blocks.and_then(|blocks| blocks.get(&network));
+
+            if let Some(block) = block {
+                used_blocks.insert(key.clone(), *block);
+            }
+
+            let avatar_info = self.lookup(address, &network, block).await;
+
+            avatars.insert(key, [(AvatarType::Flat, avatar_info)].into());
+        }
+
+        (avatars, used_blocks)
+    }
+
+    /// The avatar of `address` on one network, at `block` or the latest block, moderated and
+    /// annotated with its confirmation.
+    async fn lookup<T: AvatarLookup>(&self, address: &Address, network: &SupportedNetworks, block: Option<&BlockRef>) -> Option<T> {
+        let provider = rpc::client(network);
+
+        let block_id = block.map_or_else(BlockId::latest, |block| BlockId::from(block.hash));
+
+        let mut maybe_avatar_info = T::fetch(provider, address, block_id, &self.cache).await.ok();
+
+        if let Some(avatar_info) = maybe_avatar_info.as_mut() {
+            let confirmed = self.events.read(network).await.confirmation(address, avatar_info.avatar(), block.map(|block| block.number));
+            avatar_info.set_confirmed(confirmed);
+        }
+
+        // Signed avatars only fill in for wallets without an on-chain avatar, they have no history
+        if block.is_none() && maybe_avatar_info.as_ref().is_none_or(|avatar_info| avatar_info.avatar().token_address == Address::ZERO) {
+            if let Some(signed_avatar) = self.signed_avatars.get(network, address).await {
+                if let Ok(avatar_info) = T::fetch_signed(provider, address, &signed_avatar, &self.cache).await {
+                    maybe_avatar_info = Some(avatar_info);
                 }
             }
         }
+
+        if let Some(avatar_info) = maybe_avatar_info.as_mut() {
+            let avatar = avatar_info.avatar();
+
+            if self.moderation.is_blocked(network, &avatar.token_address, &avatar.token_id).await {
+                avatar_info.moderate();
+            }
+        }
+
+        maybe_avatar_info
     }
 
+    /// Looks the avatar up on `networks` and picks the one `policy` prefers.
     #[allow(clippy::missing_errors_doc)]
-    #[allow(clippy::missing_panics_doc)]
-    pub async fn get_info_with_metadata(&self, address: &Address, networks: impl IntoIterator<Item=SupportedNetworks>) -> eyre::Result<AvatarInfoWithMetadataResponse> {
-        let mut response = AvatarInfoWithMetadataResponse::default();
+    pub async fn get_primary(&self, address: &Address, networks: Vec<SupportedNetworks>, blocks: Option<&Blocks>, policy: &PrimaryPolicy) -> eyre::Result<PrimaryAvatarResponse> {
+        self.select_primary(address, networks, blocks, policy, true).await
+    }
+
+    // Without `fetch_times`, only the block times the index already knows are used
+    async fn select_primary(&self, address: &Address, networks: Vec<SupportedNetworks>, blocks: Option<&Blocks>, policy: &PrimaryPolicy, fetch_times: bool) -> eyre::Result<PrimaryAvatarResponse> {
+        let mut response = self.get_info_with_metadata(address, networks.clone(), blocks).await?;
 
-        let networks: Vec<SupportedNetworks> = networks.into_iter().collect();
+        let mut candidates = Vec::new();
 
         for network in networks {
-            let provider = match network {
-                SupportedNetworks::Ethereum => rpc::ethereum::new(),
-                SupportedNetworks::Sepolia => rpc::sepolia::new(),
-                SupportedNetworks::Polygon => rpc::polygon::new(),
-                SupportedNetworks::Base => rpc::base::new(),
+            let key = network.to_string().to_lowercase();
+
+            let Some(avatar_info) = response.networks.remove(&key).and_then(|mut avatars| avatars.remove(&AvatarType::Flat)).flatten() else {
+                continue;
+            };
+
+            // Moderated avatars can't be shown, wallets with only those get the fallback image
+            if avatar_info.moderated {
+                continue;
+            }
+
+            let block_number = response.blocks.get(&key).map(|block| block.number);
+            // Avatars set since the last background sync have no timestamp yet
+            let set_at = if fetch_times {
+                self.events.set_at(&network, address, &avatar_info.avatar, block_number).await
+            } else {
+                self.events.read(&network).await.set_at(address, &avatar_info.avatar, block_number)
             };
-            
-            let maybe_avatar_info = provider.get_avatar_info_with_metadata(address, self.cache.clone()).await.ok();
 
-            response.networks.insert(network.to_string().to_lowercase(), [(AvatarType::Flat, maybe_avatar_info)].into());
+            candidates.push(Candidate { network, avatar_info, set_at });
         }
 
-        Ok(response)
+        let Some((primary, reason)) = policy.select(candidates) else {
+            return Ok(PrimaryAvatarResponse {
+                fallback: true,
+                fallback_image: Some(blockies::path(address)),
+                ..Default::default()
+            });
+        };
+
+        let key = primary.network.to_string().to_lowercase();
+
+        Ok(PrimaryAvatarResponse {
+            block: response.blocks.remove(&key),
+            network: Some(key),
+            avatar: Some(primary.avatar_info),
+            reason: Some(reason),
+            set_at: primary.set_at,
+            fallback: false,
+            fallback_image: None,
+        })
     }
 
-    pub async fn listen_contract_events(&self) {
+    /// HTTP URL of the primary avatar's image, `None` without an avatar or a fetchable image.
+    ///
+    /// Redirects are on the path of every image load, so the primary avatar is picked without
+    /// the recency criterion and without fetching block times.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn get_image_url(&self, address: &Address, networks: Vec<SupportedNetworks>) -> eyre::Result<Option<String>> {
+        let primary = self.select_primary(address, networks, None, &PRIMARY_POLICY.without_recency(), false).await?;
+
+        Ok(primary.avatar
+            .and_then(|avatar_info| avatar_info.avatar_metadata.image)
+            .and_then(|image| rpc::http_url(&image)))
+    }
+
+    /// Looks a token up by itself, moderated like an avatar would be.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn get_nft(&self, network: &SupportedNetworks, contract: &Address, token_id: U256) -> eyre::Result<NftInfo> {
+        let mut nft_info = rpc::client(network).get_nft(contract, token_id, &self.cache).await?;
+
+        if self.moderation.is_blocked(network, contract, &token_id).await {
+            nft_info.moderate();
+        }
+
+        Ok(nft_info)
+    }
+
+    /// Resolves the avatar set by `event` as of the event's block.
+    pub async fn resolve_event(&self, network: &SupportedNetworks, event: &AvatarSetEvent) -> Option<AvatarInfoWithMetadata> {
+        let block = BlockId::number(event.block_number);
+
+        let mut avatar_info = rpc::client(network).get_avatar_info_with_metadata(&event.wallet, block, self.cache.clone()).await.ok()?;
+
+        if self.moderation.is_blocked(network, &avatar_info.avatar.token_address, &avatar_info.avatar.token_id).await {
+            avatar_info.moderate();
+        }
+
+        // Only confirmed events are broadcast
+        avatar_info.confirmed = Some(true);
+
+        Some(avatar_info)
+    }
+
+    /// Follows new `AvatarSet` events on every network with a deploy block, feeding the event index
+    /// and its subscribers.
+    pub fn spawn_event_follower(self: &Arc<Self>, period: Duration) -> JoinHandle<()> {
+        let avatar_service = self.clone();
+
+        let networks: Vec<SupportedNetworks> = SupportedNetworks::all().into_iter()
+            .filter(|network| {
+                let indexed = rpc::client(network).deploy_block().is_some();
+
+                if !indexed {
+                    warn!(target: "API", "{}_AVATAR_SERVICE_DEPLOY_BLOCK not set, not indexing AvatarSet events on {network}", network.to_string().to_uppercase());
+                }
+
+                indexed
+            })
+            .collect();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            loop {
+                interval.tick().await;
+
+                for network in &networks {
+                    if let Err(err) = avatar_service.events.sync(network).await {
+                        error!(target: "API", "Failed to sync AvatarSet events on {network}: {err}");
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use alloy::primitives::{address, Address, U256};
+
+    use crate::models::avatar::{Avatar, AvatarInfo, AvatarSource, AvatarType};
+    use crate::services::avatar::{is_fallback, NetworkAvatars};
+
+    fn avatar_info(token_address: Address, moderated: bool) -> AvatarInfo {
+        AvatarInfo {
+            chain_id: String::new(),
+            account_id: String::new(),
+            avatar: Avatar { token_address, token_id: U256::from(1), asset_id: None },
+            owned: true,
+            uri: String::new(),
+            moderated,
+            source: AvatarSource::Onchain,
+            confirmed: None,
+        }
+    }
+
+    fn networks(avatars: Vec<Option<AvatarInfo>>) -> NetworkAvatars<AvatarInfo> {
+        avatars.into_iter()
+            .enumerate()
+            .map(|(position, avatar_info)| (position.to_string(), HashMap::from([(AvatarType::Flat, avatar_info)])))
+            .collect()
+    }
+
+    #[test]
+    fn test_is_fallback() {
+        let collection = address!("907808732079863886443057C65827a0F1c64357");
+
+        assert!(is_fallback(&networks(vec![None, Some(avatar_info(Address::ZERO, false))])));
+        assert!(is_fallback(&networks(vec![Some(avatar_info(collection, true))])));
 
+        assert!(!is_fallback(&networks(vec![Some(avatar_info(collection, true)), Some(avatar_info(collection, false))])));
     }
 }
@@ -0,0 +1,141 @@
+use std::fmt::Write;
+
+use alloy::primitives::Address;
+
+// Cells per side of the identicon
+const SIZE: usize = 8;
+// Pixels per cell in the rendered SVG
+const SCALE: usize = 8;
+
+/// Path of the fallback image served for `address`.
+pub fn path(address: &Address) -> String {
+    format!("/avatar/{address}/fallback.svg")
+}
+
+/// Renders the Ethereum blockies identicon of `address` as SVG.
+///
+/// Follows the reference `ethereum-blockies` implementation, seeded with the lowercase address,
+/// so the image matches what wallets already show for the address.
+pub fn svg(address: &Address) -> String {
+    let blockie = Blockie::new(address);
+    let side = SIZE * SCALE;
+
+    let mut svg = format!(r#"<svg xmlns="http://www.w3.org/2000/svg" width="{side}" height="{side}" viewBox="0 0 {side} {side}" shape-rendering="crispEdges">"#);
+    let _ = write!(svg, r#"<rect width="{side}" height="{side}" fill="{}"/>"#, blockie.background);
+
+    for (position, cell) in blockie.cells.iter().enumerate() {
+        let fill = match cell {
+            1 => &blockie.color,
+            2 => &blockie.spot_color,
+            _ => continue
+        };
+
+        let (x, y) = (position % SIZE * SCALE, position / SIZE * SCALE);
+        let _ = write!(svg, r#"<rect x="{x}" y="{y}" width="{SCALE}" height="{SCALE}" fill="{fill}"/>"#);
+    }
+
+    svg.push_str("</svg>");
+
+    svg
+}
+
+struct Blockie {
+    color: String,
+    background: String,
+    spot_color: String,
+    // Row-major cells, 0 for background, 1 for color and 2 for spot color
+    cells: Vec<u8>
+}
+
+impl Blockie {
+    fn new(address: &Address) -> Self {
+        let mut random = Random::new(&address.to_string().to_lowercase());
+
+        let color = random.color();
+        let background = random.color();
+        let spot_color = random.color();
+
+        let mut cells = Vec::with_capacity(SIZE * SIZE);
+
+        for _ in 0..SIZE {
+            // The left half is random and mirrored onto the right half
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let half: Vec<u8> = (0..SIZE.div_ceil(2)).map(|_| (random.sample() * 2.3).floor() as u8).collect();
+
+            cells.extend(&half);
+            cells.extend(half.iter().rev().skip(SIZE % 2));
+        }
+
+        Self { color, background, spot_color, cells }
+    }
+}
+
+/// The xorshift generator of `ethereum-blockies`, including its JavaScript integer semantics.
+struct Random([i32; 4]);
+
+impl Random {
+    fn new(seed: &str) -> Self {
+        let mut state = [0i32; 4];
+
+        for (i, byte) in seed.bytes().enumerate() {
+            state[i % 4] = (state[i % 4] << 5).wrapping_sub(state[i % 4]).wrapping_add(i32::from(byte));
+        }
+
+        Self(state)
+    }
+
+    // In [0, 2), the reference divides the unsigned state by 2^31
+    fn sample(&mut self) -> f64 {
+        let t = self.0[0] ^ (self.0[0] << 11);
+
+        self.0[0] = self.0[1];
+        self.0[1] = self.0[2];
+        self.0[2] = self.0[3];
+        self.0[3] = self.0[3] ^ (self.0[3] >> 19) ^ t ^ (t >> 8);
+
+        #[allow(clippy::cast_sign_loss)]
+        let unsigned = self.0[3] as u32;
+
+        f64::from(unsigned) / f64::from(1u32 << 31)
+    }
+
+    fn color(&mut self) -> String {
+        let hue = (self.sample() * 360.0).floor();
+        let saturation = self.sample() * 60.0 + 40.0;
+        let lightness = (self.sample() + self.sample() + self.sample() + self.sample()) * 25.0;
+
+        format!("hsl({hue},{saturation:.2}%,{lightness:.2}%)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+
+    use crate::services::blockies::{svg, Blockie, SIZE};
+
+    // Reference values from the `ethereum-blockies` JavaScript implementation
+    #[test]
+    fn test_blockie_matches_reference() {
+        let blockie = Blockie::new(&address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045"));
+
+        assert_eq!(blockie.color, "hsl(240,99.41%,49.27%)");
+        assert_eq!(blockie.background, "hsl(325,48.64%,22.81%)");
+        assert_eq!(blockie.spot_color, "hsl(155,95.38%,59.94%)");
+        assert_eq!(blockie.cells[..SIZE], [0, 1, 1, 1, 1, 1, 1, 0]);
+
+        for row in blockie.cells.chunks(SIZE) {
+            assert!(row.iter().eq(row.iter().rev()));
+        }
+    }
+
+    #[test]
+    fn test_svg_is_deterministic() {
+        let vitalik = address!("d8dA6BF26964aF9D7eEd9e03E53415D37aA96045");
+        let other = address!("0000000000000000000000000000000000000001");
+
+        assert_eq!(svg(&vitalik), svg(&vitalik));
+        assert_ne!(svg(&vitalik), svg(&other));
+        assert!(svg(&vitalik).starts_with("<svg"));
+    }
+}
@@ -0,0 +1,848 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use alloy::primitives::{Address, B256, U256};
+use alloy::rpc::types::eth::BlockNumberOrTag;
+use log::{error, info, warn};
+use tokio::sync::{broadcast, Mutex, RwLock, RwLockReadGuard};
+
+use crate::models::avatar::{Avatar, AvatarCollection};
+use crate::models::block::BlockRef;
+use crate::models::event::AvatarSetEvent;
+use crate::response::page::{PageParams, PageResponse};
+use crate::response::stats::{CollectionUsageResponse, NetworkStatsResponse};
+use crate::services::rpc::{self, BlockSource};
+use crate::supported_networks::SupportedNetworks;
+
+const DEFAULT_LOG_CHUNK_SIZE: u64 = 2_000;
+const SECONDS_PER_DAY: u64 = 86_400;
+
+// Events buffered per subscriber before it starts lagging
+const BROADCAST_CAPACITY: usize = 1_024;
+
+// Blocks per `eth_getLogs` request, kept below the range limits of common RPC providers
+static EVENT_LOG_CHUNK_SIZE: LazyLock<u64> = LazyLock::new(|| {
+    std::env::var("EVENT_LOG_CHUNK_SIZE")
+        .map(|value| value.parse::<u64>().expect("EVENT_LOG_CHUNK_SIZE must be a number of blocks"))
+        .unwrap_or(DEFAULT_LOG_CHUNK_SIZE)
+        .max(1)
+});
+
+// Seconds between background syncs of all networks, 0 disables event indexing
+pub static EVENT_POLL_INTERVAL: LazyLock<Option<Duration>> = LazyLock::new(|| {
+    let seconds = std::env::var("EVENT_POLL_INTERVAL")
+        .map(|value| value.parse::<u64>().expect("EVENT_POLL_INTERVAL must be a number of seconds"))
+        .unwrap_or(15);
+
+    (seconds > 0).then(|| Duration::from_secs(seconds))
+});
+
+/// `AvatarSet` events of one network in chain order, indexed by wallet.
+///
+/// Events are indexed up to the chain head, those within the network's confirmation depth are
+/// pending and rolled back if their block is reorganized away.
+#[derive(Default)]
+pub struct NetworkIndex {
+    synced_to: Option<u64>,
+    head: Option<u64>,
+    confirmations: u64,
+    // Hashes of the pending blocks with events and of the last synced head, checked against the
+    // chain on every sync
+    block_hashes: BTreeMap<u64, B256>,
+    // Last block whose events were broadcast, only confirmed blocks are
+    broadcast_to: Option<u64>,
+    events: Vec<AvatarSetEvent>,
+    by_wallet: HashMap<Address, Vec<usize>>,
+    // Position of each wallet's latest event, i.e. its current avatar
+    current: HashMap<Address, usize>,
+    // Wallets whose current avatar is from the collection
+    users_by_collection: HashMap<Address, HashSet<Address>>,
+    // Wallets with a current, non-cleared avatar
+    active_avatars: usize,
+    // Avatar sets per day since the Unix epoch
+    daily_sets: BTreeMap<u64, usize>,
+    block_timestamps: HashMap<u64, u64>,
+    // Block times interpolated during syncs, only precise enough for the daily counters
+    estimated_timestamps: HashMap<u64, u64>
+}
+
+impl NetworkIndex {
+    pub fn synced_to(&self) -> Option<u64> {
+        self.synced_to
+    }
+
+    pub fn events(&self) -> &[AvatarSetEvent] {
+        &self.events
+    }
+
+    /// Last block whose events were broadcast, later events reach subscribers live.
+    pub fn broadcast_to(&self) -> Option<u64> {
+        self.broadcast_to
+    }
+
+    /// Whether `block_number` has at least the network's confirmations on top of it.
+    pub fn is_confirmed(&self, block_number: u64) -> bool {
+        self.head.is_some_and(|head| head.saturating_sub(block_number) >= self.confirmations)
+    }
+
+    // Highest confirmed block, if the head is deep enough to have one
+    fn confirmed_to(&self) -> Option<u64> {
+        self.head.and_then(|head| head.checked_sub(self.confirmations))
+    }
+
+    /// Whether the `avatar` looked up for `wallet`, at `block_number` or the latest block, is final.
+    ///
+    /// `None` until the network is synced. An avatar that doesn't match the wallet's latest
+    /// indexed event was set after the last sync and is reported as pending.
+    pub fn confirmation(&self, wallet: &Address, avatar: &Avatar, block_number: Option<u64>) -> Option<bool> {
+        self.head?;
+
+        if let Some(block_number) = block_number {
+            return Some(self.is_confirmed(block_number));
+        }
+
+        match self.current.get(wallet).map(|position| &self.events[*position]) {
+            Some(event) if event.token_address == avatar.token_address && event.token_id == avatar.token_id => {
+                Some(self.is_confirmed(event.block_number))
+            }
+            None if avatar.token_address == Address::ZERO => Some(true),
+            _ => Some(false)
+        }
+    }
+
+    fn push(&mut self, event: AvatarSetEvent) {
+        if let Some(timestamp) = event.timestamp {
+            self.block_timestamps.insert(event.block_number, timestamp);
+        }
+
+        let day_timestamp = event.timestamp.or_else(|| self.estimated_timestamps.get(&event.block_number).copied());
+
+        if let Some(timestamp) = day_timestamp {
+            *self.daily_sets.entry(timestamp / SECONDS_PER_DAY).or_default() += 1;
+        }
+
+        let (position, wallet, token_address) = (self.events.len(), event.wallet, event.token_address);
+
+        if let Some(previous) = self.current.insert(wallet, position) {
+            let previous_collection = self.events[previous].token_address;
+
+            if let Some(users) = self.users_by_collection.get_mut(&previous_collection) {
+                users.remove(&wallet);
+                self.active_avatars -= 1;
+
+                if users.is_empty() {
+                    self.users_by_collection.remove(&previous_collection);
+                }
+            }
+        }
+
+        // Setting the zero address clears the avatar
+        if token_address != Address::ZERO {
+            self.users_by_collection.entry(token_address).or_default().insert(wallet);
+            self.active_avatars += 1;
+        }
+
+        self.by_wallet.entry(wallet).or_default().push(position);
+        self.events.push(event);
+    }
+
+    /// The event that set `avatar` for `wallet`, as of `block_number` or the latest block. `None`
+    /// when the index doesn't hold that event.
+    pub fn set_by(&self, wallet: &Address, avatar: &Avatar, block_number: Option<u64>) -> Option<&AvatarSetEvent> {
+        let event = self.history(wallet).find(|event| block_number.is_none_or(|block_number| event.block_number <= block_number))?;
+
+        (event.token_address == avatar.token_address && event.token_id == avatar.token_id).then_some(event)
+    }
+
+    /// Block timestamp of the event that set `avatar`, when it is known.
+    pub fn set_at(&self, wallet: &Address, avatar: &Avatar, block_number: Option<u64>) -> Option<u64> {
+        let event = self.set_by(wallet, avatar, block_number)?;
+
+        event.timestamp.or_else(|| self.block_timestamps.get(&event.block_number).copied())
+    }
+
+    /// Drops the events after `block_number` and rebuilds the wallet indexes from the rest.
+    fn rollback(&mut self, block_number: u64) {
+        let events = std::mem::take(&mut self.events);
+        let mut block_timestamps = std::mem::take(&mut self.block_timestamps);
+        let mut estimated_timestamps = std::mem::take(&mut self.estimated_timestamps);
+        let mut block_hashes = std::mem::take(&mut self.block_hashes);
+
+        block_timestamps.retain(|number, _| *number <= block_number);
+        estimated_timestamps.retain(|number, _| *number <= block_number);
+        block_hashes.retain(|number, _| *number <= block_number);
+
+        *self = Self {
+            synced_to: Some(block_number),
+            head: self.head,
+            confirmations: self.confirmations,
+            block_hashes,
+            broadcast_to: self.broadcast_to.map(|broadcast_to| broadcast_to.min(block_number)),
+            block_timestamps,
+            estimated_timestamps,
+            ..Self::default()
+        };
+
+        for event in events.into_iter().take_while(|event| event.block_number <= block_number) {
+            self.push(event);
+        }
+    }
+
+    /// Events of `wallet`, most recent first.
+    pub fn history(&self, wallet: &Address) -> impl Iterator<Item = &AvatarSetEvent> {
+        self.by_wallet.get(wallet)
+            .into_iter()
+            .flat_map(|positions| positions.iter().rev())
+            .map(move |position| &self.events[*position])
+    }
+
+    /// Current avatars using `collection`, or only its `token_id`, most recently set first.
+    pub fn users(&self, collection: &Address, token_id: Option<U256>) -> Vec<&AvatarSetEvent> {
+        let mut users: Vec<_> = self.users_by_collection.get(collection)
+            .into_iter()
+            .flatten()
+            .map(|wallet| &self.events[self.current[wallet]])
+            .filter(|event| token_id.is_none_or(|token_id| event.token_id == token_id))
+            .collect();
+
+        users.sort_by(|a, b| (b.block_number, b.log_index).cmp(&(a.block_number, a.log_index)));
+
+        users
+    }
+
+    /// Usage counters of the index, `top` collections by active avatars and the last `days` of sets.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn stats(&self, verified_collections: Option<&HashMap<Address, AvatarCollection>>, top: usize, days: u64) -> NetworkStatsResponse {
+        let is_verified = |contract: &Address| verified_collections.is_some_and(|collections| collections.contains_key(contract));
+
+        let verified_avatars: usize = self.users_by_collection.iter()
+            .filter(|(contract, _)| is_verified(contract))
+            .map(|(_, users)| users.len())
+            .sum();
+
+        let mut top_collections: Vec<_> = self.users_by_collection.iter()
+            .map(|(contract, users)| CollectionUsageResponse {
+                contract: *contract,
+                name: verified_collections.and_then(|collections| collections.get(contract)).and_then(|collection| collection.name.clone()),
+                verified: is_verified(contract),
+                users: users.len(),
+            })
+            .collect();
+
+        top_collections.sort_by(|a, b| b.users.cmp(&a.users).then(a.contract.cmp(&b.contract)));
+        top_collections.truncate(top);
+
+        let today = crate::services::whitelist::unix_timestamp() / SECONDS_PER_DAY;
+
+        let daily_avatar_sets = self.daily_sets.range(today.saturating_sub(days.saturating_sub(1))..)
+            .filter_map(|(day, count)| {
+                let timestamp = i64::try_from(day * SECONDS_PER_DAY).ok()?;
+                let date = chrono::DateTime::from_timestamp(timestamp, 0)?;
+
+                Some((date.format("%Y-%m-%d").to_string(), *count))
+            })
+            .collect();
+
+        NetworkStatsResponse {
+            synced_to: self.synced_to,
+            avatar_sets: self.events.len(),
+            wallets_with_avatar: self.active_avatars,
+            verified_avatars,
+            unverified_avatars: self.active_avatars - verified_avatars,
+            verified_ratio: (self.active_avatars > 0).then(|| verified_avatars as f64 / self.active_avatars as f64),
+            top_collections,
+            daily_avatar_sets,
+        }
+    }
+
+    /// The event with its block timestamp and confirmation state.
+    pub fn annotate(&self, event: &AvatarSetEvent) -> AvatarSetEvent {
+        AvatarSetEvent {
+            timestamp: event.timestamp.or_else(|| self.block_timestamps.get(&event.block_number).copied()),
+            confirmed: Some(self.is_confirmed(event.block_number)),
+            ..event.clone()
+        }
+    }
+}
+
+/// Local index of `AvatarSet` logs, synced from `eth_getLogs` by the background event follower.
+///
+/// Lookups read the index as it is and never sync it themselves.
+pub struct EventIndex {
+    networks: HashMap<SupportedNetworks, RwLock<NetworkIndex>>,
+    // Serializes syncs per network, so concurrent lookups don't scan the same range twice
+    sync_locks: HashMap<SupportedNetworks, Mutex<()>>,
+    sender: broadcast::Sender<(SupportedNetworks, AvatarSetEvent)>
+}
+
+impl Default for EventIndex {
+    fn default() -> Self {
+        Self {
+            networks: SupportedNetworks::all().into_iter().map(|network| (network, RwLock::default())).collect(),
+            sync_locks: SupportedNetworks::all().into_iter().map(|network| (network, Mutex::default())).collect(),
+            sender: broadcast::channel(BROADCAST_CAPACITY).0,
+        }
+    }
+}
+
+impl EventIndex {
+    pub async fn read(&self, network: &SupportedNetworks) -> RwLockReadGuard<'_, NetworkIndex> {
+        self.networks[network].read().await
+    }
+
+    /// Whether events of the network are indexed at all, which takes a non-zero `EVENT_POLL_INTERVAL`
+    /// and the network's `<NETWORK>_AVATAR_SERVICE_DEPLOY_BLOCK`.
+    pub fn is_enabled(network: &SupportedNetworks) -> bool {
+        EVENT_POLL_INTERVAL.is_some() && rpc::client(network).deploy_block().is_some()
+    }
+
+    /// Whether the network has been synced at least once, before that the index is empty.
+    pub async fn is_indexed(&self, network: &SupportedNetworks) -> bool {
+        self.read(network).await.synced_to.is_some()
+    }
+
+    /// Receives events as they become confirmed, the initial scan of a network is not broadcast.
+    pub fn subscribe(&self) -> broadcast::Receiver<(SupportedNetworks, AvatarSetEvent)> {
+        self.sender.subscribe()
+    }
+
+    /// Scans the logs between the last synced block (or the contract deployment) and the chain head.
+    ///
+    /// Progress is stored after every chunk, so a failed sync resumes where it stopped. Before
+    /// scanning, the hashes of pending blocks are compared with the chain and the events of
+    /// reorganized blocks are rolled back and scanned again.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn sync(&self, network: &SupportedNetworks) -> eyre::Result<()> {
+        self.sync_from(network, rpc::client(network)).await
+    }
+
+    async fn sync_from(&self, network: &SupportedNetworks, client: &dyn BlockSource) -> eyre::Result<()> {
+        let Some(deploy_block) = client.deploy_block() else {
+            eyre::bail!("No deploy block configured for {network}");
+        };
+
+        let _sync_lock = self.sync_locks[network].lock().await;
+
+        let (mut synced_to, block_hashes, confirmed_to) = {
+            let index = self.read(network).await;
+            (index.synced_to, index.block_hashes.clone(), index.confirmed_to())
+        };
+
+        let head = client.get_block(BlockNumberOrTag::Latest).await?;
+
+        // A lagging endpoint can report a head below the indexed one
+        if synced_to.is_some_and(|synced_to| head.number < synced_to) {
+            return Ok(());
+        }
+
+        // Blocks up to the confirmed one are final, without one every indexed event is pending
+        let final_block = confirmed_to.unwrap_or_else(|| deploy_block.saturating_sub(1));
+
+        if let Some(fork_block) = Self::find_fork(client, &block_hashes, final_block).await? {
+            warn!(target: "API", "Reorg detected on {network}, rolling back AvatarSet events after block {fork_block}");
+
+            let mut index = self.networks[network].write().await;
+
+            if index.broadcast_to.is_some_and(|broadcast_to| broadcast_to > fork_block) {
+                error!(target: "API", "Reorg on {network} is deeper than {} confirmations, broadcast events were reverted", index.confirmations);
+            }
+
+            index.rollback(fork_block);
+            synced_to = Some(fork_block);
+        }
+
+        {
+            let mut index = self.networks[network].write().await;
+            index.head = Some(head.number);
+            index.confirmations = client.confirmations();
+        }
+
+        let mut from = synced_to.map_or(deploy_block, |synced_to| synced_to + 1);
+
+        while from <= head.number {
+            let to = head.number.min(from + *EVENT_LOG_CHUNK_SIZE - 1);
+
+            let events = client.get_avatar_set_events(from, to).await?;
+
+            // Daily counters only need the day of an event, its block time is interpolated between
+            // the chunk bounds instead of fetching every block. Exact times are fetched on demand.
+            let estimated_timestamps = if events.iter().any(|event| event.timestamp.is_none()) {
+                let (first, last) = (client.get_block(from.into()).await?, client.get_block(to.into()).await?);
+
+                events.iter()
+                    .map(|event| (event.block_number, interpolate(&first, &last, event.block_number)))
+                    .collect()
+            } else {
+                HashMap::new()
+            };
+
+            if !events.is_empty() {
+                info!(target: "API", "Indexed {} AvatarSet events on {network} in blocks {from}-{to}", events.len());
+            }
+
+            let mut index = self.networks[network].write().await;
+
+            index.estimated_timestamps.extend(estimated_timestamps);
+
+            for event in events {
+                if !index.is_confirmed(event.block_number) {
+                    index.block_hashes.insert(event.block_number, event.block_hash);
+                }
+
+                index.push(event);
+            }
+
+            index.synced_to = Some(to);
+
+            from = to + 1;
+        }
+
+        let mut index = self.networks[network].write().await;
+
+        index.block_hashes.insert(head.number, head.hash);
+
+        if let Some(confirmed_to) = index.confirmed_to() {
+            index.block_hashes.retain(|number, _| *number > confirmed_to || *number == head.number);
+
+            // The events confirmed by this sync, skipped on the initial scan
+            if let Some(broadcast_to) = index.broadcast_to {
+                let mut confirmed: Vec<_> = index.events.iter()
+                    .rev()
+                    .take_while(|event| event.block_number > broadcast_to)
+                    .filter(|event| event.block_number <= confirmed_to)
+                    .map(|event| index.annotate(event))
+                    .collect();
+
+                confirmed.reverse();
+
+                for event in confirmed {
+                    // Fails only without subscribers
+                    let _ = self.sender.send((network.clone(), event));
+                }
+            }
+
+            index.broadcast_to = Some(confirmed_to.max(index.broadcast_to.unwrap_or_default()));
+        }
+
+        Ok(())
+    }
+
+    /// Walks the tracked block hashes from the newest down and returns the newest one still on the
+    /// chain, or `final_block` when none is. `None` when the newest still matches.
+    ///
+    /// Only blocks with events are tracked, so the blocks between a matching one and the next
+    /// tracked one may have been reorganized as well.
+    async fn find_fork(client: &dyn BlockSource, block_hashes: &BTreeMap<u64, B256>, final_block: u64) -> eyre::Result<Option<u64>> {
+        for (position, (number, hash)) in block_hashes.iter().rev().enumerate() {
+            if client.get_block((*number).into()).await?.hash == *hash {
+                return Ok((position > 0).then_some(*number));
+            }
+        }
+
+        Ok((!block_hashes.is_empty()).then_some(final_block))
+    }
+
+    /// Block timestamp of the event that set `avatar` for `wallet`, fetching the time of its block
+    /// when the index doesn't know it yet.
+    pub async fn set_at(&self, network: &SupportedNetworks, wallet: &Address, avatar: &Avatar, block_number: Option<u64>) -> Option<u64> {
+        let event_block = {
+            let index = self.read(network).await;
+            let event = index.set_by(wallet, avatar, block_number)?;
+
+            if let Some(timestamp) = event.timestamp.or_else(|| index.block_timestamps.get(&event.block_number).copied()) {
+                return Some(timestamp);
+            }
+
+            event.block_number
+        };
+
+        self.resolve_timestamps(network, [event_block]).await;
+
+        self.read(network).await.set_at(wallet, avatar, block_number)
+    }
+
+    /// A page of the wallet's `AvatarSet` events, most recent first, with block timestamps.
+    pub async fn history(&self, network: &SupportedNetworks, wallet: &Address, page: &PageParams) -> PageResponse<AvatarSetEvent> {
+        let response = {
+            let index = self.read(network).await;
+            PageResponse::new(index.history(wallet).cloned(), page)
+        };
+
+        self.annotate(network, response).await
+    }
+
+    /// A page of the wallets currently using `collection` (or one of its tokens) as avatar.
+    pub async fn users(&self, network: &SupportedNetworks, collection: &Address, token_id: Option<U256>, page: &PageParams) -> PageResponse<AvatarSetEvent> {
+        let response = {
+            let index = self.read(network).await;
+            PageResponse::new(index.users(collection, token_id).into_iter().cloned(), page)
+        };
+
+        self.annotate(network, response).await
+    }
+
+    async fn annotate(&self, network: &SupportedNetworks, mut response: PageResponse<AvatarSetEvent>) -> PageResponse<AvatarSetEvent> {
+        self.resolve_timestamps(network, response.items.iter().map(|event| event.block_number)).await;
+
+        let index = self.read(network).await;
+
+        for event in &mut response.items {
+            *event = index.annotate(event);
+        }
+
+        response
+    }
+
+    /// Fetches and caches the timestamps of blocks not seen yet.
+    async fn resolve_timestamps(&self, network: &SupportedNetworks, block_numbers: impl IntoIterator<Item = u64>) {
+        let missing: BTreeSet<u64> = {
+            let index = self.read(network).await;
+
+            block_numbers.into_iter()
+                .filter(|block_number| !index.block_timestamps.contains_key(block_number))
+                .collect()
+        };
+
+        let client = rpc::client(network);
+
+        for block_number in missing {
+            match client.get_block(block_number.into()).await {
+                Ok(block) => {
+                    self.networks[network].write().await.block_timestamps.insert(block_number, block.timestamp);
+                }
+                Err(err) => warn!(target: "API", "Failed to fetch block {block_number} on {network}: {err}")
+            }
+        }
+    }
+}
+
+/// Linear estimate of the time `block_number` was mined at, between two known blocks.
+fn interpolate(first: &BlockRef, last: &BlockRef, block_number: u64) -> u64 {
+    if last.number <= first.number {
+        return first.timestamp;
+    }
+
+    let elapsed = u128::from(last.timestamp.saturating_sub(first.timestamp));
+    let blocks = u128::from(block_number.saturating_sub(first.number).min(last.number - first.number));
+
+    let offset = elapsed * blocks / u128::from(last.number - first.number);
+
+    first.timestamp + u64::try_from(offset).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+    use std::sync::Mutex;
+
+    use alloy::primitives::{address, Address, B256, U256};
+    use alloy::rpc::types::eth::BlockNumberOrTag;
+
+    use crate::models::avatar::{Avatar, AvatarCollection};
+    use crate::models::block::BlockRef;
+    use crate::models::event::AvatarSetEvent;
+    use crate::services::events::{interpolate, EventIndex, NetworkIndex, SECONDS_PER_DAY};
+    use crate::services::rpc::{self, BlockSource};
+    use crate::supported_networks::SupportedNetworks;
+
+    fn event(wallet: Address, token_id: u64, block_number: u64) -> AvatarSetEvent {
+        AvatarSetEvent {
+            wallet,
+            token_address: address!("907808732079863886443057C65827a0F1c64357"),
+            token_id: U256::from(token_id),
+            block_number,
+            block_hash: B256::ZERO,
+            transaction_hash: B256::ZERO,
+            log_index: 0,
+            timestamp: None,
+            confirmed: None,
+        }
+    }
+
+    #[test]
+    fn test_history_is_most_recent_first() {
+        let alice = address!("0000000000000000000000000000000000000001");
+        let bob = address!("0000000000000000000000000000000000000002");
+
+        let mut index = NetworkIndex::default();
+        index.push(event(alice, 1, 10));
+        index.push(event(bob, 2, 11));
+        index.push(event(alice, 3, 12));
+
+        let token_ids: Vec<_> = index.history(&alice).map(|event| event.token_id).collect();
+
+        assert_eq!(token_ids, vec![U256::from(3), U256::from(1)]);
+        assert_eq!(index.history(&address!("0000000000000000000000000000000000000003")).count(), 0);
+    }
+
+    #[test]
+    fn test_users_follow_current_avatar() {
+        let alice = address!("0000000000000000000000000000000000000001");
+        let bob = address!("0000000000000000000000000000000000000002");
+        let collection = address!("907808732079863886443057C65827a0F1c64357");
+
+        let mut index = NetworkIndex::default();
+        index.push(event(alice, 1, 10));
+        index.push(event(bob, 1, 11));
+        index.push(event(alice, 2, 12));
+
+        let wallets: Vec<_> = index.users(&collection, None).iter().map(|event| event.wallet).collect();
+        assert_eq!(wallets, vec![alice, bob]);
+
+        let wallets: Vec<_> = index.users(&collection, Some(U256::from(1))).iter().map(|event| event.wallet).collect();
+        assert_eq!(wallets, vec![bob]);
+
+        index.push(AvatarSetEvent { token_address: Address::ZERO, ..event(bob, 0, 13) });
+        assert_eq!(index.users(&collection, None).len(), 1);
+    }
+
+    #[test]
+    fn test_stats() {
+        let alice = address!("0000000000000000000000000000000000000001");
+        let bob = address!("0000000000000000000000000000000000000002");
+        let collection = address!("907808732079863886443057C65827a0F1c64357");
+        let other = address!("00000000000000000000000000000000000000aa");
+
+        let mut index = NetworkIndex::default();
+        index.push(event(alice, 1, 10));
+        index.push(event(bob, 1, 11));
+        index.push(AvatarSetEvent { token_address: other, ..event(alice, 5, 12) });
+
+        let verified = HashMap::from([(collection, AvatarCollection { name: Some("Punks".to_string()), verified: true, ..Default::default() })]);
+
+        let stats = index.stats(Some(&verified), 1, 30);
+
+        assert_eq!(stats.avatar_sets, 3);
+        assert_eq!(stats.wallets_with_avatar, 2);
+        assert_eq!(stats.verified_avatars, 1);
+        assert_eq!(stats.unverified_avatars, 1);
+        assert_eq!(stats.verified_ratio, Some(0.5));
+        assert_eq!(stats.top_collections.len(), 1);
+    }
+
+    #[test]
+    fn test_rollback_rebuilds_wallet_indexes() {
+        let alice = address!("0000000000000000000000000000000000000001");
+        let bob = address!("0000000000000000000000000000000000000002");
+        let collection = address!("907808732079863886443057C65827a0F1c64357");
+
+        let mut index = NetworkIndex::default();
+        index.push(event(alice, 1, 10));
+        index.push(event(bob, 1, 11));
+        index.push(AvatarSetEvent { token_address: Address::ZERO, ..event(alice, 0, 12) });
+
+        index.rollback(11);
+
+        assert_eq!(index.synced_to(), Some(11));
+        assert_eq!(index.events().len(), 2);
+        assert_eq!(index.history(&alice).count(), 1);
+        assert_eq!(index.users(&collection, None).len(), 2);
+    }
+
+    #[test]
+    fn test_confirmation() {
+        let alice = address!("0000000000000000000000000000000000000001");
+        let collection = address!("907808732079863886443057C65827a0F1c64357");
+        let avatar = |token_id: u64| Avatar { token_address: collection, token_id: U256::from(token_id), asset_id: None };
+
+        let mut index = NetworkIndex::default();
+        index.push(event(alice, 1, 10));
+        index.push(event(alice, 2, 20));
+
+        assert_eq!(index.confirmation(&alice, &avatar(2), None), None);
+
+        index.head = Some(25);
+        index.confirmations = 10;
+
+        assert!(index.is_confirmed(10));
+        assert!(!index.is_confirmed(20));
+        assert_eq!(index.confirmation(&alice, &avatar(2), None), Some(false));
+        assert_eq!(index.confirmation(&alice, &avatar(2), Some(12)), Some(true));
+        // Set after the last sync
+        assert_eq!(index.confirmation(&alice, &avatar(3), None), Some(false));
+
+        index.head = Some(30);
+        assert_eq!(index.confirmation(&alice, &avatar(2), None), Some(true));
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let first = BlockRef { number: 100, hash: B256::ZERO, timestamp: 1_000 };
+        let last = BlockRef { number: 200, hash: B256::ZERO, timestamp: 2_200 };
+
+        assert_eq!(interpolate(&first, &last, 100), 1_000);
+        assert_eq!(interpolate(&first, &last, 150), 1_600);
+        assert_eq!(interpolate(&first, &last, 200), 2_200);
+        assert_eq!(interpolate(&first, &first, 100), 1_000);
+    }
+
+    #[test]
+    fn test_daily_sets_use_estimated_timestamps() {
+        let alice = address!("0000000000000000000000000000000000000001");
+
+        let mut index = NetworkIndex::default();
+        index.estimated_timestamps.insert(10, 3 * SECONDS_PER_DAY + 5);
+        index.push(event(alice, 1, 10));
+        index.push(event(alice, 2, 11));
+
+        assert_eq!(index.daily_sets.get(&3), Some(&1));
+        // Estimates are not reported as block times
+        assert_eq!(index.annotate(&index.events()[0]).timestamp, None);
+
+        index.rollback(10);
+        assert_eq!(index.daily_sets.get(&3), Some(&1));
+    }
+
+    #[derive(Default)]
+    struct ChainState {
+        head: u64,
+        // Blocks from this one on were replaced by another branch
+        reorged_from: Option<u64>,
+        events: Vec<AvatarSetEvent>
+    }
+
+    impl ChainState {
+        fn hash(&self, number: u64) -> B256 {
+            let branch = u64::from(self.reorged_from.is_some_and(|reorged_from| number >= reorged_from));
+
+            B256::from(U256::from(number * 2 + branch))
+        }
+    }
+
+    #[derive(Default)]
+    struct FakeChain(Mutex<ChainState>);
+
+    impl FakeChain {
+        fn update(&self, update: impl FnOnce(&mut ChainState)) {
+            update(&mut self.0.lock().unwrap());
+        }
+
+        fn hash(&self, number: u64) -> B256 {
+            self.0.lock().unwrap().hash(number)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl BlockSource for FakeChain {
+        async fn get_block(&self, number: BlockNumberOrTag) -> eyre::Result<BlockRef> {
+            let state = self.0.lock().unwrap();
+
+            let number = match number {
+                BlockNumberOrTag::Latest => state.head,
+                BlockNumberOrTag::Number(number) if number <= state.head => number,
+                _ => return Err(rpc::Error::UnknownBlock.into())
+            };
+
+            Ok(BlockRef { number, hash: state.hash(number), timestamp: 1_000 + number * 12 })
+        }
+
+        fn confirmations(&self) -> u64 {
+            10
+        }
+
+        fn deploy_block(&self) -> Option<u64> {
+            Some(0)
+        }
+
+        async fn get_avatar_set_events(&self, from: u64, to: u64) -> eyre::Result<Vec<AvatarSetEvent>> {
+            let state = self.0.lock().unwrap();
+
+            Ok(state.events.iter()
+                .filter(|event| (from..=to).contains(&event.block_number))
+                .map(|event| AvatarSetEvent {
+                    block_hash: state.hash(event.block_number),
+                    timestamp: Some(1_000 + event.block_number * 12),
+                    ..event.clone()
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find_fork() {
+        let chain = FakeChain::default();
+        chain.update(|state| state.head = 100);
+
+        // Only blocks with pending events and the head are tracked
+        let block_hashes: BTreeMap<_, _> = [95, 100].into_iter().map(|number| (number, chain.hash(number))).collect();
+
+        assert_eq!(EventIndex::find_fork(&chain, &block_hashes, 90).await.unwrap(), None);
+
+        // Blocks 96-99 aren't tracked, so the newest matching block is the one to keep
+        chain.update(|state| state.reorged_from = Some(97));
+        assert_eq!(EventIndex::find_fork(&chain, &block_hashes, 90).await.unwrap(), Some(95));
+
+        chain.update(|state| state.reorged_from = Some(93));
+        assert_eq!(EventIndex::find_fork(&chain, &block_hashes, 90).await.unwrap(), Some(90));
+
+        assert_eq!(EventIndex::find_fork(&chain, &BTreeMap::new(), 90).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_sync_rescans_reorged_blocks_without_events() {
+        let alice = address!("0000000000000000000000000000000000000001");
+        let bob = address!("0000000000000000000000000000000000000002");
+        let network = SupportedNetworks::Polygon;
+
+        let events = EventIndex::default();
+        let chain = FakeChain::default();
+
+        chain.update(|state| {
+            state.head = 100;
+            state.events = vec![event(alice, 1, 95)];
+        });
+
+        events.sync_from(&network, &chain).await.unwrap();
+        assert_eq!(events.read(&network).await.block_hashes.keys().copied().collect::<Vec<_>>(), vec![95, 100]);
+
+        // The new branch has an event in a block that had none
+        chain.update(|state| {
+            state.head = 102;
+            state.reorged_from = Some(97);
+            state.events.push(event(bob, 2, 97));
+        });
+
+        events.sync_from(&network, &chain).await.unwrap();
+
+        let index = events.read(&network).await;
+
+        assert_eq!(index.events().len(), 2);
+        assert_eq!(index.history(&bob).map(|event| event.block_number).collect::<Vec<_>>(), vec![97]);
+        assert_eq!(index.synced_to(), Some(102));
+    }
+
+    #[tokio::test]
+    async fn test_sync_broadcasts_newly_confirmed_events() {
+        let alice = address!("0000000000000000000000000000000000000001");
+        let network = SupportedNetworks::Polygon;
+
+        let events = EventIndex::default();
+        let chain = FakeChain::default();
+
+        chain.update(|state| {
+            state.head = 100;
+            state.events = vec![event(alice, 1, 85), event(alice, 2, 95)];
+        });
+
+        // The initial scan isn't broadcast
+        events.sync_from(&network, &chain).await.unwrap();
+
+        let mut receiver = events.subscribe();
+
+        chain.update(|state| state.head = 104);
+        events.sync_from(&network, &chain).await.unwrap();
+        assert!(receiver.try_recv().is_err());
+
+        chain.update(|state| state.head = 105);
+        events.sync_from(&network, &chain).await.unwrap();
+
+        let (broadcast_network, event) = receiver.try_recv().unwrap();
+
+        assert_eq!(broadcast_network, network);
+        assert_eq!((event.block_number, event.token_id, event.confirmed), (95, U256::from(2), Some(true)));
+        assert!(receiver.try_recv().is_err());
+
+        assert_eq!(events.read(&network).await.broadcast_to(), Some(95));
+    }
+}
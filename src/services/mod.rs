@@ -1,2 +1,16 @@
+pub mod admin;
 pub mod avatar;
+pub mod blockies;
+pub mod events;
+pub mod moderation;
+pub mod primary;
+pub mod rate_limit;
+pub mod relayer;
 pub mod rpc;
+pub mod signature;
+pub mod signed_avatar;
+pub mod siwe;
+pub mod store;
+pub mod stream;
+pub mod webhook;
+pub mod whitelist;
@@ -0,0 +1,345 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::LazyLock;
+
+use alloy::primitives::{Address, U256};
+use log::warn;
+use reqwest::StatusCode;
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+use crate::models::moderation;
+use crate::response::moderation::BlockedContractResponse;
+use crate::services::store::Store;
+use crate::supported_networks::SupportedNetworks;
+
+// Optional, only manually blocked avatars are moderated when unset
+pub static BLOCKLIST_URL: LazyLock<Option<String>> = LazyLock::new(|| {
+    std::env::var("BLOCKLIST_URL").ok()
+});
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Failed to fetch blocklist: {0}")]
+    Fetch(#[source] reqwest::Error),
+    #[error("Blocklist source responded with {0}")]
+    Status(StatusCode),
+    #[error("Failed to decode blocklist: {0}")]
+    Decode(#[source] reqwest::Error),
+    #[error("Failed to persist manual blocks: {0}")]
+    Persist(eyre::Report)
+}
+
+#[derive(Clone, Debug)]
+pub struct BlockedEntry {
+    // `None` blocks every token of the contract
+    pub token_ids: Option<BTreeSet<U256>>,
+    pub reason: Option<String>
+}
+
+impl BlockedEntry {
+    pub fn blocks(&self, token_id: &U256) -> bool {
+        self.token_ids.as_ref().is_none_or(|token_ids| token_ids.contains(token_id))
+    }
+}
+
+pub type Blocklist = HashMap<SupportedNetworks, HashMap<Address, BlockedEntry>>;
+
+fn insert(blocklist: &mut Blocklist, network: SupportedNetworks, contract: Address, token_ids: Option<BTreeSet<U256>>, reason: Option<String>) {
+    match blocklist.entry(network).or_default().entry(contract) {
+        Entry::Vacant(entry) => {
+            entry.insert(BlockedEntry { token_ids, reason });
+        }
+        Entry::Occupied(mut entry) => {
+            let entry = entry.get_mut();
+
+            match (&mut entry.token_ids, token_ids) {
+                (Some(blocked), Some(token_ids)) => blocked.extend(token_ids),
+                (blocked, _) => *blocked = None
+            }
+
+            if reason.is_some() {
+                entry.reason = reason;
+            }
+        }
+    }
+}
+
+// The blocklist format of `BLOCKLIST_URL`, used to persist the manual entries
+fn to_model(blocklist: &Blocklist) -> moderation::Blocklist {
+    let networks = blocklist.iter()
+        .map(|(network, contracts)| {
+            let contracts = contracts.iter()
+                .map(|(contract, entry)| moderation::BlockedContract {
+                    contract: contract.to_string(),
+                    token_ids: entry.token_ids.iter().flatten().map(ToString::to_string).collect(),
+                    reason: entry.reason.clone(),
+                })
+                .collect();
+
+            (network.to_string().to_lowercase(), contracts)
+        })
+        .collect();
+
+    moderation::Blocklist(networks)
+}
+
+/// Blocked contracts and tokens, either loaded from `BLOCKLIST_URL` or added through the admin endpoints.
+///
+/// Both lists are kept apart so that reloading the source never drops manual entries, the manual
+/// ones are persisted when a store is given.
+#[derive(Default)]
+pub struct ModerationService {
+    source: RwLock<Blocklist>,
+    manual: RwLock<Blocklist>,
+    store: Option<Store>
+}
+
+impl ModerationService {
+    /// Restores the manual entries saved in `store`.
+    #[allow(clippy::missing_errors_doc)]
+    pub fn load(store: Store) -> eyre::Result<Self> {
+        let (manual, warnings) = validate(store.load()?.unwrap_or_default());
+
+        for warning in warnings {
+            warn!(target: "API", "Skipped persisted manual block: {warning}");
+        }
+
+        Ok(Self { manual: RwLock::new(manual), store: Some(store), ..Default::default() })
+    }
+
+    pub async fn is_blocked(&self, network: &SupportedNetworks, contract: &Address, token_id: &U256) -> bool {
+        let blocks = |blocklist: &Blocklist| {
+            blocklist.get(network)
+                .and_then(|contracts| contracts.get(contract))
+                .is_some_and(|entry| entry.blocks(token_id))
+        };
+
+        blocks(&*self.manual.read().await) || blocks(&*self.source.read().await)
+    }
+
+    /// Reloads the source blocklist, returning warnings for skipped entries.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn reload(&self) -> Result<Vec<String>, Error> {
+        let Some(url) = BLOCKLIST_URL.as_deref() else {
+            return Ok(Vec::new());
+        };
+
+        let response = reqwest::get(url).await.map_err(Error::Fetch)?;
+
+        if !response.status().is_success() {
+            return Err(Error::Status(response.status()));
+        }
+
+        let blocklist = response.json::<moderation::Blocklist>().await.map_err(Error::Decode)?;
+
+        let (blocklist, warnings) = validate(blocklist);
+
+        *self.source.write().await = blocklist;
+
+        Ok(warnings)
+    }
+
+    /// Blocks the given tokens, or the whole contract when `token_ids` is `None`.
+    ///
+    /// The block applies right away, even when it couldn't be persisted.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn block(&self, network: SupportedNetworks, contract: Address, token_ids: Option<BTreeSet<U256>>, reason: Option<String>) -> Result<(), Error> {
+        let mut manual = self.manual.write().await;
+
+        insert(&mut manual, network, contract, token_ids, reason);
+
+        self.persist(&manual).await
+    }
+
+    /// Removes manual blocks for the given tokens, or for the whole contract when `token_ids` is `None`.
+    ///
+    /// Returns `false` when nothing was blocked manually.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn unblock(&self, network: &SupportedNetworks, contract: &Address, token_ids: Option<&BTreeSet<U256>>) -> Result<bool, Error> {
+        let mut manual = self.manual.write().await;
+
+        if !remove(&mut manual, network, contract, token_ids) {
+            return Ok(false);
+        }
+
+        self.persist(&manual).await?;
+
+        Ok(true)
+    }
+
+    // Called with the lock of `manual` held, so saves happen in the order of the changes
+    async fn persist(&self, manual: &Blocklist) -> Result<(), Error> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+
+        store.save(&to_model(manual)).await.map_err(Error::Persist)
+    }
+
+    pub async fn list(&self) -> Vec<BlockedContractResponse> {
+        let source = self.source.read().await;
+        let manual = self.manual.read().await;
+
+        let mut response: Vec<BlockedContractResponse> = manual.iter()
+            .flat_map(|(network, contracts)| contracts.iter().map(move |(contract, entry)| BlockedContractResponse::new(network, contract, entry, true)))
+            .chain(source.iter().flat_map(|(network, contracts)| contracts.iter().map(move |(contract, entry)| BlockedContractResponse::new(network, contract, entry, false))))
+            .collect();
+
+        response.sort_by(|a, b| a.network.to_string().cmp(&b.network.to_string()).then(a.contract.cmp(&b.contract)));
+
+        response
+    }
+}
+
+fn remove(blocklist: &mut Blocklist, network: &SupportedNetworks, contract: &Address, token_ids: Option<&BTreeSet<U256>>) -> bool {
+    let Some(contracts) = blocklist.get_mut(network) else {
+        return false;
+    };
+
+    let Some(entry) = contracts.get_mut(contract) else {
+        return false;
+    };
+
+    match (&mut entry.token_ids, token_ids) {
+        (Some(blocked), Some(token_ids)) => {
+            blocked.retain(|token_id| !token_ids.contains(token_id));
+
+            if blocked.is_empty() {
+                contracts.remove(contract);
+            }
+        }
+        // Individual tokens of a fully blocked contract can't be carved out
+        (None, Some(_)) => return false,
+        (_, None) => {
+            contracts.remove(contract);
+        }
+    }
+
+    true
+}
+
+pub fn validate(blocklist: moderation::Blocklist) -> (Blocklist, Vec<String>) {
+    let mut validated = Blocklist::new();
+    let mut warnings = Vec::new();
+
+    for (network, contracts) in blocklist.0 {
+        let Ok(chain) = network.parse::<SupportedNetworks>() else {
+            warnings.push(format!("Unknown network '{network}' ({} contracts skipped)", contracts.len()));
+            continue;
+        };
+
+        for blocked in contracts {
+            let Ok(address) = blocked.contract.parse::<Address>() else {
+                warnings.push(format!("Invalid contract address '{}' on {network}", blocked.contract));
+                continue;
+            };
+
+            let mut token_ids = BTreeSet::new();
+
+            for token_id in &blocked.token_ids {
+                match token_id.parse::<U256>() {
+                    Ok(token_id) => { token_ids.insert(token_id); }
+                    Err(_) => warnings.push(format!("Invalid token id '{token_id}' for {address} on {network}"))
+                }
+            }
+
+            let token_ids = if blocked.token_ids.is_empty() {
+                None
+            } else if token_ids.is_empty() {
+                // Every token id was invalid, skip rather than blocking the whole contract by accident
+                continue;
+            } else {
+                Some(token_ids)
+            };
+
+            insert(&mut validated, chain.clone(), address, token_ids, blocked.reason);
+        }
+    }
+
+    (validated, warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeSet, HashMap};
+
+    use alloy::primitives::{address, U256};
+
+    use crate::models::moderation;
+    use crate::services::moderation::{validate, ModerationService};
+    use crate::services::store::Store;
+    use crate::supported_networks::SupportedNetworks;
+
+    #[tokio::test]
+    async fn test_token_and_contract_blocks() {
+        let collection = address!("907808732079863886443057C65827a0F1c64357");
+        let moderation = ModerationService::default();
+
+        moderation.block(SupportedNetworks::Polygon, collection, Some(BTreeSet::from([U256::from(1)])), None).await.unwrap();
+
+        assert!(moderation.is_blocked(&SupportedNetworks::Polygon, &collection, &U256::from(1)).await);
+        assert!(!moderation.is_blocked(&SupportedNetworks::Polygon, &collection, &U256::from(2)).await);
+        assert!(!moderation.is_blocked(&SupportedNetworks::Ethereum, &collection, &U256::from(1)).await);
+
+        // Blocking the contract covers every token
+        moderation.block(SupportedNetworks::Polygon, collection, None, Some("Abuse".to_string())).await.unwrap();
+        assert!(moderation.is_blocked(&SupportedNetworks::Polygon, &collection, &U256::from(2)).await);
+
+        // Tokens of a blocked contract can't be unblocked one by one
+        assert!(!moderation.unblock(&SupportedNetworks::Polygon, &collection, Some(&BTreeSet::from([U256::from(2)]))).await.unwrap());
+        assert!(moderation.unblock(&SupportedNetworks::Polygon, &collection, None).await.unwrap());
+        assert!(!moderation.is_blocked(&SupportedNetworks::Polygon, &collection, &U256::from(1)).await);
+    }
+
+    #[tokio::test]
+    async fn test_unblock_tokens() {
+        let collection = address!("907808732079863886443057C65827a0F1c64357");
+        let moderation = ModerationService::default();
+
+        moderation.block(SupportedNetworks::Base, collection, Some(BTreeSet::from([U256::from(1), U256::from(2)])), None).await.unwrap();
+
+        assert!(moderation.unblock(&SupportedNetworks::Base, &collection, Some(&BTreeSet::from([U256::from(1)]))).await.unwrap());
+        assert!(!moderation.is_blocked(&SupportedNetworks::Base, &collection, &U256::from(1)).await);
+        assert!(moderation.is_blocked(&SupportedNetworks::Base, &collection, &U256::from(2)).await);
+
+        assert!(!moderation.unblock(&SupportedNetworks::Ethereum, &collection, None).await.unwrap());
+    }
+
+    #[test]
+    fn test_validate_skips_invalid_entries() {
+        let blocklist = moderation::Blocklist(HashMap::from([
+            ("polygon".to_string(), vec![
+                moderation::BlockedContract { contract: "0x907808732079863886443057C65827a0F1c64357".to_string(), token_ids: vec!["1".to_string(), "x".to_string()], reason: None },
+                moderation::BlockedContract { contract: "0x00000000000000000000000000000000000000aa".to_string(), token_ids: vec!["x".to_string()], reason: None },
+                moderation::BlockedContract { contract: "not-an-address".to_string(), token_ids: Vec::new(), reason: None },
+            ]),
+            ("solana".to_string(), Vec::new()),
+        ]));
+
+        let (validated, warnings) = validate(blocklist);
+
+        let polygon = &validated[&SupportedNetworks::Polygon];
+        let entry = &polygon[&address!("907808732079863886443057C65827a0F1c64357")];
+
+        assert_eq!(polygon.len(), 1);
+        assert!(entry.blocks(&U256::from(1)));
+        assert!(!entry.blocks(&U256::from(2)));
+        assert_eq!(warnings.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_manual_blocks_are_persisted() {
+        let dir = std::env::temp_dir().join(format!("eas-api-moderation-{}", rand::random::<u64>()));
+        let collection = address!("907808732079863886443057C65827a0F1c64357");
+
+        let moderation = ModerationService::load(Store::at(dir.join("moderation.json"))).unwrap();
+        moderation.block(SupportedNetworks::Polygon, collection, Some(BTreeSet::from([U256::from(7)])), Some("Abuse".to_string())).await.unwrap();
+
+        let restored = ModerationService::load(Store::at(dir.join("moderation.json"))).unwrap();
+        assert!(restored.is_blocked(&SupportedNetworks::Polygon, &collection, &U256::from(7)).await);
+        assert!(!restored.is_blocked(&SupportedNetworks::Polygon, &collection, &U256::from(8)).await);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,296 @@
+use std::cmp::Ordering;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+use alloy::primitives::Address;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::models::avatar::AvatarInfoWithMetadata;
+use crate::supported_networks::{SupportedNetworks, UnknownNetwork};
+
+const DEFAULT_CRITERIA: &str = "owned,verified,recent";
+const DEFAULT_PRIORITY: [SupportedNetworks; 4] = [
+    SupportedNetworks::Ethereum,
+    SupportedNetworks::Base,
+    SupportedNetworks::Polygon,
+    SupportedNetworks::Sepolia,
+];
+
+// Policy used when a request doesn't bring its own
+pub static PRIMARY_POLICY: LazyLock<PrimaryPolicy> = LazyLock::new(|| {
+    let criteria = std::env::var("PRIMARY_AVATAR_POLICY").unwrap_or_else(|_| DEFAULT_CRITERIA.to_string());
+    let priority = std::env::var("PRIMARY_AVATAR_NETWORKS").unwrap_or_default();
+
+    PrimaryPolicy::new(&criteria, &priority).expect("Invalid PRIMARY_AVATAR_POLICY or PRIMARY_AVATAR_NETWORKS")
+});
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Unknown primary avatar criterion: {0}")]
+    UnknownCriterion(String),
+    #[error(transparent)]
+    UnknownNetwork(#[from] UnknownNetwork)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Criterion {
+    // Drops avatars whose token the wallet no longer owns
+    Owned,
+    // Prefers collections on the whitelist
+    Verified,
+    // Prefers the avatar set last, by block timestamp
+    Recent,
+    // Prefers networks by their position in the priority list
+    Priority
+}
+
+impl FromStr for Criterion {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "owned" => Ok(Criterion::Owned),
+            "verified" => Ok(Criterion::Verified),
+            "recent" => Ok(Criterion::Recent),
+            "priority" => Ok(Criterion::Priority),
+            _ => Err(Error::UnknownCriterion(s.to_string()))
+        }
+    }
+}
+
+/// Why an avatar was picked over the others.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrimaryReason {
+    #[serde(rename = "only_candidate")]
+    OnlyCandidate,
+    #[serde(rename = "owned")]
+    Owned,
+    #[serde(rename = "verified")]
+    Verified,
+    #[serde(rename = "most_recent")]
+    MostRecent,
+    #[serde(rename = "priority")]
+    Priority
+}
+
+impl From<Criterion> for PrimaryReason {
+    fn from(value: Criterion) -> Self {
+        match value {
+            Criterion::Owned => PrimaryReason::Owned,
+            Criterion::Verified => PrimaryReason::Verified,
+            Criterion::Recent => PrimaryReason::MostRecent,
+            Criterion::Priority => PrimaryReason::Priority,
+        }
+    }
+}
+
+/// An avatar found on one network, competing to be the primary one.
+pub struct Candidate {
+    pub network: SupportedNetworks,
+    pub avatar_info: AvatarInfoWithMetadata,
+    // Timestamp of the block the avatar was set in, if indexed
+    pub set_at: Option<u64>
+}
+
+impl Candidate {
+    fn is_verified(&self) -> bool {
+        self.avatar_info.avatar_metadata.collection.as_ref().is_some_and(|collection| collection.verified)
+    }
+}
+
+/// Criteria applied in order to pick one avatar out of several networks.
+///
+/// The network priority always breaks the remaining ties, networks missing from the priority
+/// list follow in the default order.
+#[derive(Debug, Clone)]
+pub struct PrimaryPolicy {
+    criteria: Vec<Criterion>,
+    priority: Vec<SupportedNetworks>
+}
+
+impl PrimaryPolicy {
+    /// Parses comma separated criteria (`owned`, `verified`, `recent`, `priority`) and networks.
+    #[allow(clippy::missing_errors_doc)]
+    pub fn new(criteria: &str, priority: &str) -> Result<Self, Error> {
+        Ok(Self { criteria: parse_criteria(criteria)?, priority: parse_priority(priority)? })
+    }
+
+    /// The policy with the criteria and/or network priority given by a request replaced.
+    #[allow(clippy::missing_errors_doc)]
+    pub fn with_overrides(&self, criteria: Option<&str>, priority: Option<&str>) -> Result<Self, Error> {
+        Ok(Self {
+            criteria: criteria.map_or_else(|| Ok(self.criteria.clone()), parse_criteria)?,
+            priority: priority.map_or_else(|| Ok(self.priority.clone()), parse_priority)?,
+        })
+    }
+
+    /// Whether the policy needs to know when each avatar was set.
+    pub fn uses_recency(&self) -> bool {
+        self.criteria.contains(&Criterion::Recent)
+    }
+
+    /// The policy without the `recent` criterion, for lookups that can't wait for block times.
+    pub fn without_recency(&self) -> Self {
+        Self {
+            criteria: self.criteria.iter().copied().filter(|criterion| *criterion != Criterion::Recent).collect(),
+            priority: self.priority.clone(),
+        }
+    }
+
+    /// Picks the primary avatar, wallets without any avatar (or only unowned ones when `owned` is
+    /// a criterion) have none.
+    pub fn select(&self, mut candidates: Vec<Candidate>) -> Option<(Candidate, PrimaryReason)> {
+        candidates.retain(|candidate| candidate.avatar_info.avatar.token_address != Address::ZERO);
+
+        let before_filter = candidates.len();
+
+        if self.criteria.contains(&Criterion::Owned) {
+            candidates.retain(|candidate| candidate.avatar_info.owned);
+        }
+
+        candidates.sort_by(|a, b| self.criteria().map(|criterion| self.compare(criterion, a, b)).find(|ordering| ordering.is_ne()).unwrap_or(Ordering::Equal));
+
+        let mut candidates = candidates.into_iter();
+        let primary = candidates.next()?;
+
+        let reason = match candidates.next() {
+            None if before_filter > 1 => PrimaryReason::Owned,
+            None => PrimaryReason::OnlyCandidate,
+            Some(runner_up) => self.criteria()
+                .find(|criterion| self.compare(*criterion, &primary, &runner_up).is_ne())
+                .map_or(PrimaryReason::Priority, PrimaryReason::from)
+        };
+
+        Some((primary, reason))
+    }
+
+    fn criteria(&self) -> impl Iterator<Item = Criterion> + '_ {
+        self.criteria.iter().copied().chain(std::iter::once(Criterion::Priority))
+    }
+
+    // `Less` means `a` is preferred
+    fn compare(&self, criterion: Criterion, a: &Candidate, b: &Candidate) -> Ordering {
+        match criterion {
+            Criterion::Owned => b.avatar_info.owned.cmp(&a.avatar_info.owned),
+            Criterion::Verified => b.is_verified().cmp(&a.is_verified()),
+            Criterion::Recent => b.set_at.cmp(&a.set_at),
+            Criterion::Priority => self.rank(&a.network).cmp(&self.rank(&b.network))
+        }
+    }
+
+    fn rank(&self, network: &SupportedNetworks) -> usize {
+        self.priority.iter().position(|priority| priority == network).unwrap_or(self.priority.len())
+    }
+}
+
+fn parse_criteria(criteria: &str) -> Result<Vec<Criterion>, Error> {
+    entries(criteria).map(str::parse).collect()
+}
+
+fn parse_priority(priority: &str) -> Result<Vec<SupportedNetworks>, Error> {
+    let mut priority = entries(priority).map(str::parse).collect::<Result<Vec<SupportedNetworks>, _>>()?;
+
+    for network in DEFAULT_PRIORITY {
+        if !priority.contains(&network) {
+            priority.push(network);
+        }
+    }
+
+    Ok(priority)
+}
+
+fn entries(value: &str) -> impl Iterator<Item = &str> {
+    value.split(',').map(str::trim).filter(|entry| !entry.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, Address, U256};
+
+    use crate::models::avatar::{Avatar, AvatarCollection, AvatarInfoWithMetadata, AvatarMetadata, AvatarSource};
+    use crate::services::primary::{Candidate, PrimaryPolicy, PrimaryReason};
+    use crate::supported_networks::SupportedNetworks;
+
+    fn candidate(network: SupportedNetworks, owned: bool, verified: bool, set_at: Option<u64>) -> Candidate {
+        Candidate {
+            network,
+            avatar_info: AvatarInfoWithMetadata {
+                chain_id: String::new(),
+                account_id: String::new(),
+                avatar: Avatar { token_address: address!("907808732079863886443057C65827a0F1c64357"), token_id: U256::from(1), asset_id: None },
+                owned,
+                uri: String::new(),
+                avatar_metadata: AvatarMetadata {
+                    image: None,
+                    collection: Some(AvatarCollection { verified, ..Default::default() }),
+                },
+                moderated: false,
+                source: AvatarSource::Onchain,
+                confirmed: None,
+            },
+            set_at,
+        }
+    }
+
+    #[test]
+    fn test_select_applies_criteria_in_order() {
+        let policy = PrimaryPolicy::new("owned,verified,recent", "").unwrap();
+
+        let (primary, reason) = policy.select(vec![
+            candidate(SupportedNetworks::Ethereum, true, false, Some(300)),
+            candidate(SupportedNetworks::Polygon, true, true, Some(100)),
+            candidate(SupportedNetworks::Base, true, true, Some(200)),
+        ]).unwrap();
+
+        assert_eq!(primary.network, SupportedNetworks::Base);
+        assert_eq!(reason, PrimaryReason::MostRecent);
+
+        let (primary, reason) = policy.select(vec![
+            candidate(SupportedNetworks::Ethereum, false, true, Some(300)),
+            candidate(SupportedNetworks::Polygon, true, false, Some(100)),
+        ]).unwrap();
+
+        assert_eq!(primary.network, SupportedNetworks::Polygon);
+        assert_eq!(reason, PrimaryReason::Owned);
+    }
+
+    #[test]
+    fn test_select_falls_back_to_priority() {
+        let policy = PrimaryPolicy::new("", "polygon").unwrap();
+
+        let (primary, reason) = policy.select(vec![
+            candidate(SupportedNetworks::Ethereum, true, true, None),
+            candidate(SupportedNetworks::Polygon, true, true, None),
+        ]).unwrap();
+
+        assert_eq!(primary.network, SupportedNetworks::Polygon);
+        assert_eq!(reason, PrimaryReason::Priority);
+
+        let mut cleared = candidate(SupportedNetworks::Base, true, true, None);
+        cleared.avatar_info.avatar.token_address = Address::ZERO;
+
+        assert!(policy.select(vec![cleared]).is_none());
+    }
+
+    #[test]
+    fn test_without_recency_ignores_set_at() {
+        let policy = PrimaryPolicy::new("recent", "polygon").unwrap().without_recency();
+
+        assert!(!policy.uses_recency());
+
+        let (primary, reason) = policy.select(vec![
+            candidate(SupportedNetworks::Ethereum, true, true, Some(300)),
+            candidate(SupportedNetworks::Polygon, true, true, Some(100)),
+        ]).unwrap();
+
+        assert_eq!(primary.network, SupportedNetworks::Polygon);
+        assert_eq!(reason, PrimaryReason::Priority);
+    }
+
+    #[test]
+    fn test_new_rejects_unknown_entries() {
+        assert!(PrimaryPolicy::new("newest", "").is_err());
+        assert!(PrimaryPolicy::new("owned", "solana").is_err());
+    }
+}
@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use alloy::primitives::hex;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+// Tier applied to requests without an API key
+pub const ANONYMOUS_TIER: &str = "anonymous";
+
+const DEFAULT_ANONYMOUS_LIMIT: Limit = Limit { per_minute: 60, burst: 20 };
+
+// Hard cap on tracked clients, the least recently seen ones are evicted past it
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+// Evicted at once, so the scan runs once per this many new clients instead of on every request
+const EVICTION_BATCH: usize = MAX_TRACKED_CLIENTS / 10;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Malformed rate limit tier: {0}")]
+    MalformedTier(String),
+    #[error("Rate limit tier '{0}' needs a burst of at least 1")]
+    InvalidBurst(String),
+    #[error("Malformed rate limit API key entry: {0}")]
+    MalformedKey(String),
+    #[error("API key hash for '{0}' must be a hex encoded SHA-256 digest")]
+    InvalidHash(String),
+    #[error("API key '{0}' references unknown tier '{1}'")]
+    UnknownTier(String, String),
+    #[error("Invalid trusted proxy address: {0}")]
+    InvalidProxy(String)
+}
+
+/// Sustained rate and burst size of a tier, a `per_minute` of 0 disables limiting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limit {
+    pub per_minute: u32,
+    pub burst: u32
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ClientId {
+    Ip(IpAddr),
+    Key(String)
+}
+
+pub struct TokenBucket {
+    tokens: f64,
+    updated: Instant
+}
+
+impl TokenBucket {
+    pub fn new(limit: Limit, now: Instant) -> Self {
+        Self { tokens: f64::from(limit.burst), updated: now }
+    }
+
+    fn refill(&mut self, limit: Limit, now: Instant) {
+        let refill_rate = f64::from(limit.per_minute) / 60.0;
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+
+        self.tokens = (self.tokens + elapsed * refill_rate).min(f64::from(limit.burst));
+        self.updated = now;
+    }
+
+    /// Takes a token, or returns how long until one is available.
+    pub fn try_acquire(&mut self, limit: Limit, now: Instant) -> Result<(), Duration> {
+        self.refill(limit, now);
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+
+        let refill_rate = f64::from(limit.per_minute) / 60.0;
+
+        Err(Duration::from_secs_f64((1.0 - self.tokens) / refill_rate))
+    }
+}
+
+struct ApiKey {
+    name: String,
+    hash: [u8; 32],
+    tier: String
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Decision {
+    Allowed,
+    Limited(Duration),
+    InvalidKey
+}
+
+/// Token-bucket rate limiter keyed by client IP or API key.
+pub struct RateLimiter {
+    tiers: HashMap<String, Limit>,
+    keys: Vec<ApiKey>,
+    trusted_proxies: Vec<IpAddr>,
+    buckets: Mutex<HashMap<ClientId, (Limit, TokenBucket)>>
+}
+
+impl RateLimiter {
+    /// Reads `RATE_LIMIT_TIERS` (`name:per_minute:burst;...`), `RATE_LIMIT_API_KEYS`
+    /// (`name:sha256hex:tier;...`) and `TRUSTED_PROXIES` (comma separated addresses).
+    ///
+    /// The `anonymous` tier applies to requests without `X-Api-Key` and defaults to 60 requests
+    /// per minute with a burst of 20.
+    #[allow(clippy::missing_errors_doc)]
+    pub fn from_env() -> Result<Self, Error> {
+        let env = |name: &str| std::env::var(name).unwrap_or_default();
+
+        Self::new(&env("RATE_LIMIT_TIERS"), &env("RATE_LIMIT_API_KEYS"), &env("TRUSTED_PROXIES"))
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub fn new(tiers: &str, keys: &str, trusted_proxies: &str) -> Result<Self, Error> {
+        let mut parsed_tiers = HashMap::from([(ANONYMOUS_TIER.to_string(), DEFAULT_ANONYMOUS_LIMIT)]);
+
+        for entry in entries(tiers, ';') {
+            let parts: Vec<_> = entry.split(':').map(str::trim).collect();
+
+            let [name, per_minute, burst] = parts[..] else {
+                return Err(Error::MalformedTier(entry.to_string()));
+            };
+
+            let (Ok(per_minute), Ok(burst)) = (per_minute.parse(), burst.parse()) else {
+                return Err(Error::MalformedTier(entry.to_string()));
+            };
+
+            // A burst of 0 would reject every request, only disabled tiers may leave it at 0
+            if per_minute > 0 && burst < 1 {
+                return Err(Error::InvalidBurst(name.to_string()));
+            }
+
+            parsed_tiers.insert(name.to_string(), Limit { per_minute, burst });
+        }
+
+        let mut parsed_keys = Vec::new();
+
+        for entry in entries(keys, ';') {
+            let parts: Vec<_> = entry.split(':').map(str::trim).collect();
+
+            let [name, hash, tier] = parts[..] else {
+                return Err(Error::MalformedKey(entry.to_string()));
+            };
+
+            let hash = hex::decode(hash).ok()
+                .and_then(|hash| <[u8; 32]>::try_from(hash).ok())
+                .ok_or_else(|| Error::InvalidHash(name.to_string()))?;
+
+            if !parsed_tiers.contains_key(tier) {
+                return Err(Error::UnknownTier(name.to_string(), tier.to_string()));
+            }
+
+            parsed_keys.push(ApiKey { name: name.to_string(), hash, tier: tier.to_string() });
+        }
+
+        let trusted_proxies = entries(trusted_proxies, ',')
+            .map(|proxy| IpAddr::from_str(proxy).map_err(|_| Error::InvalidProxy(proxy.to_string())))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            tiers: parsed_tiers,
+            keys: parsed_keys,
+            trusted_proxies,
+            buckets: Mutex::default(),
+        })
+    }
+
+    /// Resolves the client address, trusting `X-Forwarded-For` only when sent by a trusted proxy.
+    ///
+    /// The header is walked from the right and the first address that is not a trusted proxy is
+    /// used, as entries to its left can be forged by the client.
+    pub fn client_ip(&self, peer: IpAddr, forwarded_for: Option<&str>) -> IpAddr {
+        if !self.trusted_proxies.contains(&peer) {
+            return peer;
+        }
+
+        let mut client = peer;
+
+        for hop in forwarded_for.unwrap_or_default().rsplit(',').map(str::trim) {
+            let Ok(address) = hop.parse::<IpAddr>() else {
+                break;
+            };
+
+            client = address;
+
+            if !self.trusted_proxies.contains(&address) {
+                break;
+            }
+        }
+
+        client
+    }
+
+    /// Charges one request to the API key if given, otherwise to the client IP.
+    pub async fn check(&self, ip: IpAddr, api_key: Option<&str>) -> Decision {
+        let (client, tier) = match api_key {
+            Some(token) => match self.authenticate(token) {
+                Some(key) => (ClientId::Key(key.name.clone()), key.tier.as_str()),
+                None => return Decision::InvalidKey
+            },
+            None => (ClientId::Ip(ip), ANONYMOUS_TIER)
+        };
+
+        let limit = self.tiers[tier];
+
+        if limit.per_minute == 0 {
+            return Decision::Allowed;
+        }
+
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().await;
+
+        if buckets.len() >= MAX_TRACKED_CLIENTS && !buckets.contains_key(&client) {
+            evict_least_recent(&mut buckets, EVICTION_BATCH);
+        }
+
+        let (_, bucket) = buckets.entry(client).or_insert_with(|| (limit, TokenBucket::new(limit, now)));
+
+        match bucket.try_acquire(limit, now) {
+            Ok(()) => Decision::Allowed,
+            Err(retry_after) => Decision::Limited(retry_after)
+        }
+    }
+
+    fn authenticate(&self, token: &str) -> Option<&ApiKey> {
+        let hash: [u8; 32] = Sha256::digest(token.as_bytes()).into();
+
+        let mut matched = None;
+
+        for key in &self.keys {
+            if bool::from(key.hash[..].ct_eq(&hash[..])) {
+                matched = Some(key);
+            }
+        }
+
+        matched
+    }
+}
+
+/// Drops at least `count` buckets, the ones whose clients were seen least recently.
+fn evict_least_recent(buckets: &mut HashMap<ClientId, (Limit, TokenBucket)>, count: usize) {
+    let mut last_seen: Vec<Instant> = buckets.values().map(|(_, bucket)| bucket.updated).collect();
+
+    if count == 0 || last_seen.is_empty() {
+        return;
+    }
+
+    let index = count.min(last_seen.len()) - 1;
+    let (_, cutoff, _) = last_seen.select_nth_unstable(index);
+    let cutoff = *cutoff;
+
+    buckets.retain(|_, (_, bucket)| bucket.updated > cutoff);
+}
+
+fn entries(value: &str, separator: char) -> impl Iterator<Item = &str> {
+    value.split(separator).map(str::trim).filter(|entry| !entry.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::IpAddr;
+    use std::time::{Duration, Instant};
+
+    use crate::services::rate_limit::{evict_least_recent, ClientId, Decision, Limit, RateLimiter, TokenBucket};
+
+    // sha256("secret")
+    const SECRET_HASH: &str = "2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b";
+
+    #[test]
+    fn test_token_bucket() {
+        let limit = Limit { per_minute: 60, burst: 2 };
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(limit, start);
+
+        assert!(bucket.try_acquire(limit, start).is_ok());
+        assert!(bucket.try_acquire(limit, start).is_ok());
+
+        let retry_after = bucket.try_acquire(limit, start).unwrap_err();
+        assert!(retry_after > Duration::from_millis(900) && retry_after <= Duration::from_secs(1));
+
+        assert!(bucket.try_acquire(limit, start + Duration::from_secs(1)).is_ok());
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let limiter = RateLimiter::new("", "", "10.0.0.1, 10.0.0.2").unwrap();
+
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let stranger: IpAddr = "203.0.113.9".parse().unwrap();
+
+        assert_eq!(limiter.client_ip(stranger, Some("198.51.100.1")), stranger);
+        assert_eq!(limiter.client_ip(proxy, Some("1.1.1.1, 198.51.100.1, 10.0.0.2")), "198.51.100.1".parse::<IpAddr>().unwrap());
+        assert_eq!(limiter.client_ip(proxy, None), proxy);
+    }
+
+    #[tokio::test]
+    async fn test_check_uses_key_tier() {
+        let limiter = RateLimiter::new("anonymous:60:1;partner:0:0", &format!("acme:{SECRET_HASH}:partner"), "").unwrap();
+        let ip: IpAddr = "203.0.113.9".parse().unwrap();
+
+        assert_eq!(limiter.check(ip, None).await, Decision::Allowed);
+        assert!(matches!(limiter.check(ip, None).await, Decision::Limited(_)));
+
+        assert_eq!(limiter.check(ip, Some("secret")).await, Decision::Allowed);
+        assert_eq!(limiter.check(ip, Some("secret")).await, Decision::Allowed);
+        assert_eq!(limiter.check(ip, Some("wrong")).await, Decision::InvalidKey);
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_config() {
+        assert!(RateLimiter::new("partner:fast:10", "", "").is_err());
+        assert!(RateLimiter::new("", &format!("acme:{SECRET_HASH}:missing"), "").is_err());
+        assert!(RateLimiter::new("", "", "not-an-ip").is_err());
+        assert!(RateLimiter::new("partner:60:0", "", "").is_err());
+    }
+
+    #[test]
+    fn test_evict_least_recent() {
+        let limit = Limit { per_minute: 60, burst: 2 };
+        let start = Instant::now();
+
+        let mut buckets: HashMap<_, _> = (0..10u8)
+            .map(|i| (ClientId::Ip(IpAddr::from([10, 0, 0, i])), (limit, TokenBucket::new(limit, start + Duration::from_secs(u64::from(i))))))
+            .collect();
+
+        evict_least_recent(&mut buckets, 3);
+
+        assert_eq!(buckets.len(), 7);
+        assert!(!buckets.contains_key(&ClientId::Ip(IpAddr::from([10, 0, 0, 2]))));
+        assert!(buckets.contains_key(&ClientId::Ip(IpAddr::from([10, 0, 0, 3]))));
+    }
+}
@@ -0,0 +1,779 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use alloy::eips::eip2718::Encodable2718;
+use alloy::network::{EthereumWallet, TransactionBuilder};
+use alloy::primitives::{hex, Address, Bytes, B256, U256};
+use alloy::providers::Provider;
+use alloy::rpc::types::eth::{TransactionReceipt, TransactionRequest};
+use alloy::signers::local::PrivateKeySigner;
+use alloy::sol_types::{SolCall, SolStruct};
+use chrono::{DateTime, NaiveDate, Utc};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::services::rpc::{self, AvatarService, Client};
+use crate::services::signature;
+use crate::services::signed_avatar::{SetAvatar, DOMAIN};
+use crate::services::store::Store;
+use crate::supported_networks::SupportedNetworks;
+
+// Hex encoded key of the account paying for relayed transactions, the relayer is disabled when unset
+static RELAYER_PRIVATE_KEY: LazyLock<Option<String>> = LazyLock::new(|| {
+    std::env::var("RELAYER_PRIVATE_KEY").ok()
+});
+
+const DEFAULT_WALLET_DAILY_LIMIT: u32 = 5;
+
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const RECEIPT_POLL_ATTEMPTS: usize = 120;
+
+// Confirmed and failed jobs are dropped once they are older than this
+const JOB_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Relayer is disabled")]
+    Disabled,
+    #[error("Unsupported chain id {0}")]
+    UnsupportedChain(U256),
+    #[error("Relaying is not available on {0}")]
+    Unavailable(SupportedNetworks),
+    #[error("Failed to check the trusted forwarder: {0}")]
+    Forwarder(eyre::Report),
+    #[error("Intent has expired")]
+    Expired,
+    #[error("Nonce must be greater than the last relayed nonce")]
+    StaleNonce,
+    #[error("Invalid signature")]
+    InvalidSignature,
+    #[error("Wallet does not own the token")]
+    NotOwner,
+    #[error("Failed to check token ownership: {0}")]
+    Ownership(eyre::Report),
+    #[error("Daily relay quota of the wallet is used up")]
+    QuotaExceeded,
+    #[error("Daily relayer budget on {0} is used up")]
+    BudgetExhausted(SupportedNetworks),
+    #[error("Failed to submit transaction: {0}")]
+    Submit(eyre::Report)
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RelayStatus {
+    Submitted,
+    Confirmed,
+    Failed
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub struct RelayJob {
+    pub id: String,
+    pub network: SupportedNetworks,
+    pub wallet: Address,
+    pub token_address: Address,
+    #[serde(serialize_with = "crate::models::avatar::serialize_u256_as_decimal", deserialize_with = "crate::models::avatar::deserialize_u256_from_decimal")]
+    pub token_id: U256,
+    pub status: RelayStatus,
+    pub tx_hash: Option<B256>,
+    pub block_number: Option<u64>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>
+}
+
+/// Spending limits of the relayer, reset every UTC day.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    // Relays accepted per wallet, across networks
+    pub wallet_daily: u32,
+    // Wei the relayer may commit per network, networks without a budget are not relayed
+    pub budgets: HashMap<SupportedNetworks, U256>
+}
+
+impl Limits {
+    /// Reads `RELAYER_WALLET_DAILY_LIMIT` and the `<NETWORK>_RELAYER_DAILY_BUDGET` of every network.
+    #[allow(clippy::missing_errors_doc)]
+    pub fn from_env() -> eyre::Result<Self> {
+        let wallet_daily = match std::env::var("RELAYER_WALLET_DAILY_LIMIT") {
+            Ok(value) => value.parse()?,
+            Err(_) => DEFAULT_WALLET_DAILY_LIMIT
+        };
+
+        let mut budgets = HashMap::new();
+
+        for network in SupportedNetworks::all() {
+            if let Ok(value) = std::env::var(format!("{}_RELAYER_DAILY_BUDGET", network.to_string().to_uppercase())) {
+                budgets.insert(network, U256::from_str(&value)?);
+            }
+        }
+
+        Ok(Self { wallet_daily, budgets })
+    }
+}
+
+/// Relays and wei committed on the current UTC day.
+#[derive(Deserialize, Serialize, Debug, Default)]
+struct Usage {
+    day: Option<NaiveDate>,
+    relays: HashMap<Address, u32>,
+    spent: HashMap<SupportedNetworks, U256>
+}
+
+impl Usage {
+    fn roll_over(&mut self, today: NaiveDate) {
+        if self.day != Some(today) {
+            *self = Self { day: Some(today), ..Default::default() };
+        }
+    }
+
+    fn check_quota(&self, limits: &Limits, wallet: &Address) -> Result<(), Error> {
+        if self.relays.get(wallet).copied().unwrap_or_default() >= limits.wallet_daily {
+            return Err(Error::QuotaExceeded);
+        }
+
+        Ok(())
+    }
+
+    /// Counts the relay against the wallet's quota and the network's budget at its maximum cost.
+    fn reserve(&mut self, limits: &Limits, network: &SupportedNetworks, wallet: &Address, cost: U256) -> Result<(), Error> {
+        self.check_quota(limits, wallet)?;
+
+        let budget = limits.budgets.get(network).copied().unwrap_or_default();
+        let spent = self.spent.get(network).copied().unwrap_or_default();
+
+        if spent.saturating_add(cost) > budget {
+            return Err(Error::BudgetExhausted(network.clone()));
+        }
+
+        *self.relays.entry(*wallet).or_default() += 1;
+        self.spent.insert(network.clone(), spent.saturating_add(cost));
+
+        Ok(())
+    }
+
+    fn release(&mut self, day: NaiveDate, network: &SupportedNetworks, wallet: &Address, cost: U256) {
+        if self.day != Some(day) {
+            return;
+        }
+
+        if let Some(relays) = self.relays.get_mut(wallet) {
+            *relays = relays.saturating_sub(1);
+        }
+
+        self.settle(day, network, cost, U256::ZERO);
+    }
+
+    // Replaces the reserved maximum cost with what the transaction actually cost
+    fn settle(&mut self, day: NaiveDate, network: &SupportedNetworks, reserved: U256, cost: U256) {
+        if self.day != Some(day) {
+            return;
+        }
+
+        if let Some(spent) = self.spent.get_mut(network) {
+            *spent = spent.saturating_sub(reserved).saturating_add(cost);
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct StoredNonce {
+    network: SupportedNetworks,
+    wallet: Address,
+    nonce: U256
+}
+
+#[derive(Deserialize, Serialize, Default)]
+struct StoredRelayer {
+    nonces: Vec<StoredNonce>,
+    usage: Usage,
+    #[serde(default)]
+    jobs: Vec<TrackedJob>
+}
+
+// A transaction accepted by the node, with what it was counted as against the limits
+#[derive(Deserialize, Serialize, Clone)]
+struct Submission {
+    tx_hash: B256,
+    day: NaiveDate,
+    reserved: U256
+}
+
+// Persisted with its submission, so receipt tracking can resume after a restart
+#[derive(Deserialize, Serialize, Clone)]
+struct TrackedJob {
+    job: RelayJob,
+    submission: Submission
+}
+
+impl TrackedJob {
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.job.status != RelayStatus::Submitted && (now - self.job.created_at).to_std().is_ok_and(|age| age > JOB_RETENTION)
+    }
+}
+
+/// Submits `setAvatar` transactions on behalf of users from their EIP-712 signed `SetAvatar` intents.
+///
+/// The transactions are sent by the relayer account with the user's address appended to the
+/// calldata, following ERC-2771. Networks are only relayed to when their AvatarService contract
+/// trusts the relayer as forwarder and a daily budget is configured.
+#[derive(Default)]
+pub struct RelayerService {
+    signer: Option<PrivateKeySigner>,
+    limits: Limits,
+    jobs: RwLock<HashMap<String, TrackedJob>>,
+    relayed_nonces: RwLock<HashMap<(SupportedNetworks, Address), U256>>,
+    usage: Mutex<Usage>,
+    // Only definitive answers are cached, a failed check is repeated on the next intent
+    trusted_forwarder: RwLock<HashMap<SupportedNetworks, bool>>,
+    // Next account nonce per network, the lock also serializes submissions
+    account_nonces: HashMap<SupportedNetworks, Mutex<Option<u64>>>,
+    store: Option<Store>,
+    // Serializes saves, so an older snapshot never overwrites a newer one
+    persist_lock: Mutex<()>
+}
+
+fn now() -> u64 {
+    u64::try_from(Utc::now().timestamp()).unwrap_or_default()
+}
+
+impl RelayerService {
+    #[allow(clippy::missing_errors_doc)]
+    pub fn from_env() -> eyre::Result<Self> {
+        let signer = RELAYER_PRIVATE_KEY.as_deref()
+            .map(str::parse::<PrivateKeySigner>)
+            .transpose()?;
+
+        Self::new(signer, Limits::from_env()?, Some(Store::new("relayer.json")))
+    }
+
+    /// Restores the relayed nonces, the usage of the day and the relay jobs from `store`.
+    #[allow(clippy::missing_errors_doc)]
+    pub fn new(signer: Option<PrivateKeySigner>, limits: Limits, store: Option<Store>) -> eyre::Result<Self> {
+        let stored = match &store {
+            Some(store) => store.load::<StoredRelayer>()?.unwrap_or_default(),
+            None => StoredRelayer::default()
+        };
+
+        let relayed_nonces = stored.nonces.into_iter()
+            .map(|stored| ((stored.network, stored.wallet), stored.nonce))
+            .collect();
+
+        let jobs = stored.jobs.into_iter()
+            .filter(|tracked| !tracked.is_expired(Utc::now()))
+            .map(|tracked| (tracked.job.id.clone(), tracked))
+            .collect();
+
+        Ok(Self {
+            signer,
+            limits,
+            jobs: RwLock::new(jobs),
+            relayed_nonces: RwLock::new(relayed_nonces),
+            usage: Mutex::new(stored.usage),
+            account_nonces: SupportedNetworks::all().into_iter().map(|network| (network, Mutex::new(None))).collect(),
+            store,
+            ..Default::default()
+        })
+    }
+
+    pub fn address(&self) -> Option<Address> {
+        self.signer.as_ref().map(PrivateKeySigner::address)
+    }
+
+    pub async fn job(&self, id: &str) -> Option<RelayJob> {
+        self.jobs.read().await.get(id).map(|tracked| tracked.job.clone())
+    }
+
+    /// Resumes tracking the receipts of restored jobs that were still pending.
+    pub async fn resume(self: &Arc<Self>) {
+        let pending: Vec<TrackedJob> = self.jobs.read().await.values()
+            .filter(|tracked| tracked.job.status == RelayStatus::Submitted)
+            .cloned()
+            .collect();
+
+        for tracked in pending {
+            info!(target: "API", "Resuming relay {} on {}", tracked.job.id, tracked.job.network);
+
+            let relayer_service = self.clone();
+
+            tokio::spawn(async move {
+                relayer_service.track(rpc::client(&tracked.job.network), tracked.job, tracked.submission).await;
+            });
+        }
+    }
+
+    /// Verifies a signed intent and submits its transaction, returning the submitted job.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn relay(self: &Arc<Self>, intent: SetAvatar, signature: Bytes) -> Result<RelayJob, Error> {
+        let network = u64::try_from(intent.chainId).ok()
+            .and_then(SupportedNetworks::from_chain_id)
+            .ok_or(Error::UnsupportedChain(intent.chainId))?;
+
+        self.relay_with(rpc::client(&network), intent, signature).await
+    }
+
+    async fn relay_with(self: &Arc<Self>, client: &'static Client, intent: SetAvatar, signature: Bytes) -> Result<RelayJob, Error> {
+        let Some(signer) = self.signer.clone() else {
+            return Err(Error::Disabled);
+        };
+
+        let network = client.chain().clone();
+
+        if intent.chainId != U256::from(network.chain_id()) {
+            return Err(Error::UnsupportedChain(intent.chainId));
+        }
+
+        if !self.limits.budgets.contains_key(&network) {
+            return Err(Error::Unavailable(network));
+        }
+
+        if u64::try_from(intent.expiry).unwrap_or(u64::MAX) <= now() {
+            return Err(Error::Expired);
+        }
+
+        let key = (network.clone(), intent.wallet);
+
+        if self.relayed_nonces.read().await.get(&key).is_some_and(|nonce| intent.nonce <= *nonce) {
+            return Err(Error::StaleNonce);
+        }
+
+        {
+            let mut usage = self.usage.lock().await;
+            usage.roll_over(Utc::now().date_naive());
+            usage.check_quota(&self.limits, &intent.wallet)?;
+        }
+
+        if !signature::verify_with(client, &intent.wallet, intent.eip712_signing_hash(&DOMAIN), &signature).await {
+            return Err(Error::InvalidSignature);
+        }
+
+        if !self.is_trusted_forwarder(client, &signer.address()).await? {
+            return Err(Error::Unavailable(network));
+        }
+
+        let owned = client.is_owner(&intent.wallet, &intent.tokenAddress, intent.tokenId).await
+            .map_err(Error::Ownership)?;
+
+        if !owned {
+            return Err(Error::NotOwner);
+        }
+
+        let submission = {
+            let mut account_nonce = self.account_nonces[&network].lock().await;
+
+            // Checked again, another intent of the wallet may have been relayed in the meantime
+            if self.relayed_nonces.read().await.get(&key).is_some_and(|nonce| intent.nonce <= *nonce) {
+                return Err(Error::StaleNonce);
+            }
+
+            let relayer = signer.address();
+
+            let nonce = match *account_nonce {
+                Some(nonce) => nonce,
+                None => client.call(|provider| async move {
+                    provider.get_transaction_count(relayer).pending().await.map_err(alloy::contract::Error::from)
+                }).await.map_err(|err| Error::Submit(err.into()))?
+            };
+
+            let submission = self.submit(client, signer, nonce, &intent).await;
+
+            // Resynchronize with the chain after a failed submission
+            *account_nonce = submission.as_ref().ok().map(|_| nonce + 1);
+
+            let submission = submission?;
+
+            // Recorded only once the node accepted the transaction
+            self.relayed_nonces.write().await.insert(key, intent.nonce);
+
+            submission
+        };
+
+        let job = RelayJob {
+            id: hex::encode(rand::random::<[u8; 16]>()),
+            network: network.clone(),
+            wallet: intent.wallet,
+            token_address: intent.tokenAddress,
+            token_id: intent.tokenId,
+            status: RelayStatus::Submitted,
+            tx_hash: Some(submission.tx_hash),
+            block_number: None,
+            error: None,
+            created_at: Utc::now(),
+        };
+
+        info!(target: "API", "Relay {} on {network} submitted: {}", job.id, submission.tx_hash);
+
+        {
+            let mut jobs = self.jobs.write().await;
+            let now = Utc::now();
+
+            jobs.retain(|_, tracked| !tracked.is_expired(now));
+            jobs.insert(job.id.clone(), TrackedJob { job: job.clone(), submission: submission.clone() });
+        }
+
+        self.persist().await;
+
+        let relayer_service = self.clone();
+        let submitted = job.clone();
+
+        tokio::spawn(async move {
+            relayer_service.track(client, submitted, submission).await;
+        });
+
+        Ok(job)
+    }
+
+    async fn is_trusted_forwarder(&self, client: &Client, relayer: &Address) -> Result<bool, Error> {
+        if let Some(trusted) = self.trusted_forwarder.read().await.get(client.chain()) {
+            return Ok(*trusted);
+        }
+
+        let trusted = client.is_trusted_forwarder(relayer).await.map_err(Error::Forwarder)?;
+
+        if !trusted {
+            warn!(target: "API", "AvatarService on {} does not trust {relayer} as forwarder, not relaying", client.chain());
+        }
+
+        self.trusted_forwarder.write().await.insert(client.chain().clone(), trusted);
+
+        Ok(trusted)
+    }
+
+    /// Signs `setAvatar` for the intent's wallet and broadcasts it, reserving its maximum cost
+    /// against the limits first. The reservation is released again when the node rejects it.
+    async fn submit(&self, client: &Client, signer: PrivateKeySigner, nonce: u64, intent: &SetAvatar) -> Result<Submission, Error> {
+        let network = client.chain();
+        let relayer = signer.address();
+
+        let call = AvatarService::setAvatarCall {
+            tokenAddress: intent.tokenAddress,
+            tokenId: intent.tokenId,
+        };
+
+        // ERC-2771: the forwarded sender is appended to the calldata
+        let mut input = call.abi_encode();
+        input.extend_from_slice(intent.wallet.as_slice());
+
+        let transaction = TransactionRequest::default()
+            .with_from(relayer)
+            .with_to(client.avatar_service())
+            .with_input(input)
+            .with_nonce(nonce)
+            .with_chain_id(network.chain_id());
+
+        let gas_limit = client.call(|provider| {
+            let transaction = transaction.clone();
+
+            async move {
+                provider.estimate_gas(&transaction).await.map_err(alloy::contract::Error::from)
+            }
+        }).await.map_err(|err| Error::Submit(err.into()))?;
+
+        let fees = client.call(|provider| async move {
+            provider.estimate_eip1559_fees(None).await.map_err(alloy::contract::Error::from)
+        }).await.map_err(|err| Error::Submit(err.into()))?;
+
+        let reserved = U256::from(gas_limit) * U256::from(fees.max_fee_per_gas);
+        let day = Utc::now().date_naive();
+
+        {
+            let mut usage = self.usage.lock().await;
+            usage.roll_over(day);
+            usage.reserve(&self.limits, network, &intent.wallet, reserved)?;
+        }
+
+        let result = async {
+            let envelope = transaction
+                .with_gas_limit(gas_limit)
+                .with_max_fee_per_gas(fees.max_fee_per_gas)
+                .with_max_priority_fee_per_gas(fees.max_priority_fee_per_gas)
+                .build(&EthereumWallet::from(signer)).await?;
+
+            client.send_raw_transaction(envelope.encoded_2718().into()).await?;
+
+            eyre::Ok(*envelope.tx_hash())
+        }.await;
+
+        match result {
+            Ok(tx_hash) => Ok(Submission { tx_hash, day, reserved }),
+            Err(err) => {
+                self.usage.lock().await.release(day, network, &intent.wallet, reserved);
+
+                Err(Error::Submit(err))
+            }
+        }
+    }
+
+    async fn update(&self, id: &str, update: impl FnOnce(&mut RelayJob)) {
+        if let Some(tracked) = self.jobs.write().await.get_mut(id) {
+            update(&mut tracked.job);
+        }
+    }
+
+    /// Polls the receipt of a submitted relay, it is only confirmed once the contract emitted
+    /// `AvatarSet` for the job's wallet and token.
+    async fn track(&self, client: &Client, job: RelayJob, submission: Submission) {
+        let (id, network) = (&job.id, client.chain());
+        let tx_hash = submission.tx_hash;
+
+        for _ in 0..RECEIPT_POLL_ATTEMPTS {
+            tokio::time::sleep(RECEIPT_POLL_INTERVAL).await;
+
+            let receipt = client.call(|provider| async move {
+                provider.get_transaction_receipt(tx_hash).await.map_err(alloy::contract::Error::from)
+            }).await;
+
+            match receipt {
+                Ok(Some(receipt)) => {
+                    let cost = U256::from(receipt.gas_used) * U256::from(receipt.effective_gas_price);
+                    self.usage.lock().await.settle(submission.day, network, submission.reserved, cost);
+
+                    let error = if !receipt.status() {
+                        Some("Transaction reverted")
+                    } else if !sets_avatar(&receipt, client.avatar_service(), &job) {
+                        Some("Transaction did not set the avatar of the wallet")
+                    } else {
+                        None
+                    };
+
+                    if let Some(error) = error {
+                        error!(target: "API", "Relay {id} on {network} failed: {error}");
+                    }
+
+                    self.update(id, |job| {
+                        job.block_number = receipt.block_number;
+                        job.status = if error.is_some() { RelayStatus::Failed } else { RelayStatus::Confirmed };
+                        job.error = error.map(ToString::to_string);
+                    }).await;
+
+                    self.persist().await;
+
+                    return;
+                }
+                Ok(None) => {}
+                Err(err) => warn!(target: "API", "Relay {id} on {network} receipt lookup failed: {err}")
+            }
+        }
+
+        // Left as submitted, the transaction may still be mined later
+        warn!(target: "API", "Relay {id} on {network} not mined after {RECEIPT_POLL_ATTEMPTS} attempts");
+    }
+
+    async fn persist(&self) {
+        let Some(store) = &self.store else {
+            return;
+        };
+
+        let _guard = self.persist_lock.lock().await;
+
+        let nonces = self.relayed_nonces.read().await.iter()
+            .map(|((network, wallet), nonce)| StoredNonce { network: network.clone(), wallet: *wallet, nonce: *nonce })
+            .collect();
+
+        let jobs = self.jobs.read().await.values().cloned().collect();
+
+        let stored = {
+            let usage = self.usage.lock().await;
+
+            StoredRelayer {
+                nonces,
+                usage: Usage { day: usage.day, relays: usage.relays.clone(), spent: usage.spent.clone() },
+                jobs,
+            }
+        };
+
+        // The relay already went through, losing the save only weakens replay protection and job tracking after a restart
+        if let Err(err) = store.save(&stored).await {
+            error!(target: "API", "Failed to persist relayer state: {err}");
+        }
+    }
+}
+
+fn sets_avatar(receipt: &TransactionReceipt, avatar_service: Address, job: &RelayJob) -> bool {
+    receipt.inner.logs().iter()
+        .filter(|log| log.inner.address == avatar_service)
+        .filter_map(|log| log.log_decode::<AvatarService::AvatarSet>().ok())
+        .any(|decoded| {
+            let data = decoded.inner.data;
+
+            data.walletAddress == job.wallet && data.tokenAddress == job.token_address && data.tokenId == job.token_id
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use alloy::primitives::{address, hex, Address, Bytes, B256, U256};
+    use alloy::providers::Provider;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+    use alloy::sol_types::{SolEvent, SolStruct};
+
+    use crate::services::relayer::{now, Error, Limits, RelayJob, RelayStatus, RelayerService, Submission, TrackedJob, Usage};
+    use crate::services::rpc::{AvatarService, Client};
+    use crate::services::signed_avatar::{SetAvatar, DOMAIN};
+    use crate::services::store::Store;
+    use crate::supported_networks::SupportedNetworks;
+
+    // First default anvil account
+    const ANVIL_PRIVATE_KEY: &str = "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    const AVATAR_SERVICE: Address = address!("00000000000000000000000000000000000000aa");
+    const TOKEN: Address = address!("00000000000000000000000000000000000000bb");
+
+    fn limits(wallet_daily: u32, budget: u64) -> Limits {
+        Limits {
+            wallet_daily,
+            budgets: HashMap::from([(SupportedNetworks::Sepolia, U256::from(budget))]),
+        }
+    }
+
+    #[test]
+    fn test_usage_limits() {
+        let limits = limits(2, 100);
+        let (wallet, network) = (Address::repeat_byte(1), SupportedNetworks::Sepolia);
+        let day = chrono::Utc::now().date_naive();
+
+        let mut usage = Usage::default();
+        usage.roll_over(day);
+
+        assert!(matches!(usage.reserve(&limits, &network, &wallet, U256::from(101)), Err(Error::BudgetExhausted(_))));
+
+        usage.reserve(&limits, &network, &wallet, U256::from(60)).unwrap();
+        assert!(matches!(usage.reserve(&limits, &network, &wallet, U256::from(60)), Err(Error::BudgetExhausted(_))));
+
+        // Settling at the actual cost frees the rest of the reservation
+        usage.settle(day, &network, U256::from(60), U256::from(20));
+        usage.reserve(&limits, &network, &wallet, U256::from(60)).unwrap();
+        assert!(matches!(usage.reserve(&limits, &network, &wallet, U256::from(1)), Err(Error::QuotaExceeded)));
+
+        usage.release(day, &network, &wallet, U256::from(60));
+        assert_eq!(usage.relays[&wallet], 1);
+        assert_eq!(usage.spent[&network], U256::from(20));
+
+        usage.roll_over(day.succ_opt().unwrap());
+        assert!(usage.relays.is_empty());
+    }
+
+    fn tracked_job(id: &str, status: RelayStatus, age: chrono::Duration) -> TrackedJob {
+        let created_at = chrono::Utc::now() - age;
+
+        TrackedJob {
+            job: RelayJob {
+                id: id.to_string(),
+                network: SupportedNetworks::Sepolia,
+                wallet: Address::repeat_byte(1),
+                token_address: TOKEN,
+                token_id: U256::from(42),
+                status,
+                tx_hash: Some(B256::repeat_byte(2)),
+                block_number: None,
+                error: None,
+                created_at,
+            },
+            submission: Submission { tx_hash: B256::repeat_byte(2), day: created_at.date_naive(), reserved: U256::from(100) },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_jobs_are_persisted() {
+        let dir = std::env::temp_dir().join(format!("eas-api-relayer-{}", rand::random::<u64>()));
+        let store = || Some(Store::at(dir.join("relayer.json")));
+
+        let relayer_service = RelayerService::new(None, limits(1, 100), store()).unwrap();
+
+        for (id, status, hours) in [("pending", RelayStatus::Submitted, 48), ("recent", RelayStatus::Confirmed, 1), ("old", RelayStatus::Failed, 48)] {
+            relayer_service.jobs.write().await.insert(id.to_string(), tracked_job(id, status, chrono::Duration::hours(hours)));
+        }
+
+        relayer_service.persist().await;
+
+        // Finished jobs past the retention are dropped, pending ones are kept to resume tracking
+        let restored = RelayerService::new(None, limits(1, 100), store()).unwrap();
+
+        assert_eq!(restored.job("pending").await.unwrap().status, RelayStatus::Submitted);
+        assert_eq!(restored.job("recent").await.unwrap().token_id, U256::from(42));
+        assert!(restored.job("old").await.is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    // AvatarService stub: answers 1 to view calls like `isTrustedForwarder` and emits `AvatarSet`
+    // for the ERC-2771 sender of `setAvatar` calls (4 + 32 + 32 + 20 bytes of calldata)
+    fn avatar_service_code() -> Vec<u8> {
+        [
+            &hex!("36605814601157600160005260206000f3")[..],
+            &hex!("5b60243560043560443560601c7f")[..],
+            AvatarService::AvatarSet::SIGNATURE_HASH.as_slice(),
+            &hex!("60006000a400")[..],
+        ].concat()
+    }
+
+    // ERC-721 stub: supports every interface and returns `owner` from `ownerOf`
+    fn token_code(owner: Address) -> Vec<u8> {
+        [
+            &hex!("60003560e01c6301ffc9a714602c5773")[..],
+            owner.as_slice(),
+            &hex!("60005260206000f35b600160005260206000f3")[..],
+        ].concat()
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local anvil node on port 8545 started with --chain-id 11155111"]
+    async fn test_relay() {
+        let client: &'static Client = Box::leak(Box::new(
+            Client::new(SupportedNetworks::Sepolia, &["http://localhost:8545".to_string()], AVATAR_SERVICE).unwrap()
+        ));
+
+        let user = PrivateKeySigner::random();
+
+        for (address, code) in [(AVATAR_SERVICE, avatar_service_code()), (TOKEN, token_code(user.address()))] {
+            client.call(|provider| {
+                let code = Bytes::from(code.clone());
+
+                async move {
+                    provider.raw_request::<_, ()>("anvil_setCode".into(), (address, code)).await.map_err(alloy::contract::Error::from)
+                }
+            }).await.unwrap();
+        }
+
+        let relayer_service = Arc::new(RelayerService::new(Some(ANVIL_PRIVATE_KEY.parse().unwrap()), limits(1, u64::MAX), None).unwrap());
+
+        let intent = |nonce: u64| {
+            let intent = SetAvatar {
+                wallet: user.address(),
+                chainId: U256::from(SupportedNetworks::Sepolia.chain_id()),
+                tokenAddress: TOKEN,
+                tokenId: U256::from(1),
+                nonce: U256::from(nonce),
+                expiry: U256::from(now() + 600),
+            };
+            let signature = user.sign_hash_sync(&intent.eip712_signing_hash(&DOMAIN)).unwrap();
+
+            (intent, Bytes::from(signature.as_bytes()))
+        };
+
+        let (first, signature) = intent(1);
+        let job = relayer_service.relay_with(client, first, signature).await.unwrap();
+        assert_eq!(job.status, RelayStatus::Submitted);
+
+        let (replayed, signature) = intent(1);
+        assert!(matches!(relayer_service.relay_with(client, replayed, signature).await, Err(Error::StaleNonce)));
+
+        let (second, signature) = intent(2);
+        assert!(matches!(relayer_service.relay_with(client, second, signature).await, Err(Error::QuotaExceeded)));
+
+        tokio::time::sleep(Duration::from_secs(6)).await;
+
+        let job = relayer_service.job(&job.id).await.unwrap();
+        assert_eq!(job.status, RelayStatus::Confirmed, "{:?}", job.error);
+        assert!(job.block_number.is_some());
+    }
+}
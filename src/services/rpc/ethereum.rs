@@ -2,19 +2,23 @@ use std::sync::LazyLock;
 
 use alloy::primitives::Address;
 
-use crate::services::rpc::Client;
+use crate::services::rpc::{confirmations, deploy_block, rpc_urls, Client};
 use crate::supported_networks::SupportedNetworks;
 
-static ETHEREUM_RPC_URL: LazyLock<String> = LazyLock::new(|| {
-    std::env::var("ETHEREUM_RPC_URL").expect("ETHEREUM_RPC_URL not set")
-});
+static ETHEREUM_RPC_URLS: LazyLock<Vec<String>> = LazyLock::new(|| rpc_urls("ETHEREUM"));
 
 static ETHEREUM_AVATAR_SERVICE: LazyLock<String> = LazyLock::new(|| {
     std::env::var("ETHEREUM_AVATAR_SERVICE").expect("ETHEREUM_AVATAR_SERVICE not set")
 });
 
-pub fn new() -> Client {
+static CLIENT: LazyLock<Client> = LazyLock::new(|| {
     let contract_address = ETHEREUM_AVATAR_SERVICE.parse::<Address>().unwrap();
-    
-    Client::new(SupportedNetworks::Ethereum, &ETHEREUM_RPC_URL, contract_address).unwrap()
-}
\ No newline at end of file
+
+    Client::new(SupportedNetworks::Ethereum, &ETHEREUM_RPC_URLS, contract_address).unwrap()
+        .with_deploy_block(deploy_block("ETHEREUM"))
+        .with_confirmations(confirmations("ETHEREUM", 12))
+});
+
+pub fn client() -> &'static Client {
+    &CLIENT
+}
@@ -0,0 +1,220 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use alloy::providers::{Provider, ProviderBuilder, ReqwestProvider};
+use alloy::transports::{RpcError, TransportErrorKind};
+use log::warn;
+use reqwest::Url;
+
+use crate::supported_networks::SupportedNetworks;
+
+// Consecutive failures after which an endpoint is skipped
+const FAILURE_THRESHOLD: u32 = 3;
+// How long an open circuit is skipped before it is tried again
+const OPEN_DURATION: Duration = Duration::from_secs(30);
+
+#[derive(Default)]
+struct Breaker {
+    failures: u32,
+    open_until: Option<Instant>
+}
+
+impl Breaker {
+    // An open circuit becomes half-open after `OPEN_DURATION`, letting the next call probe it
+    fn is_available(&self, now: Instant) -> bool {
+        self.open_until.is_none_or(|open_until| now >= open_until)
+    }
+
+    fn record_success(&mut self) {
+        *self = Self::default();
+    }
+
+    fn record_failure(&mut self, now: Instant) {
+        self.failures += 1;
+
+        if self.failures >= FAILURE_THRESHOLD {
+            self.open_until = Some(now + OPEN_DURATION);
+        }
+    }
+}
+
+pub struct Endpoint {
+    url: Url,
+    provider: ReqwestProvider,
+    breaker: Mutex<Breaker>
+}
+
+impl Endpoint {
+    fn new(url: Url) -> Self {
+        let provider = ProviderBuilder::new().on_http(url.clone());
+
+        Self { url, provider, breaker: Mutex::default() }
+    }
+
+    fn is_available(&self, now: Instant) -> bool {
+        self.breaker.lock().unwrap().is_available(now)
+    }
+
+    fn record_success(&self) {
+        self.breaker.lock().unwrap().record_success();
+    }
+
+    fn record_failure(&self) {
+        self.breaker.lock().unwrap().record_failure(Instant::now());
+    }
+}
+
+/// Ordered RPC endpoints of a network, each behind its own circuit breaker.
+pub struct Endpoints {
+    chain: SupportedNetworks,
+    endpoints: Vec<Endpoint>,
+    timeout: Duration,
+    round_robin: bool,
+    next: AtomicUsize
+}
+
+impl Endpoints {
+    #[allow(clippy::missing_errors_doc)]
+    pub fn new(chain: SupportedNetworks, rpc_urls: &[String], timeout: Duration, round_robin: bool) -> eyre::Result<Self> {
+        let endpoints = rpc_urls.iter()
+            .map(|rpc_url| rpc_url.parse::<Url>().map(Endpoint::new))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if endpoints.is_empty() {
+            eyre::bail!("No RPC URL configured for {chain}");
+        }
+
+        Ok(Self { chain, endpoints, timeout, round_robin, next: AtomicUsize::new(0) })
+    }
+
+    /// URL of the highest priority endpoint with a closed circuit.
+    pub fn preferred_url(&self) -> &Url {
+        let now = Instant::now();
+
+        let endpoint = self.endpoints.iter()
+            .find(|endpoint| endpoint.is_available(now))
+            .unwrap_or(&self.endpoints[0]);
+
+        &endpoint.url
+    }
+
+    /// Endpoints in the order they should be tried, open circuits are kept as a last resort.
+    fn order(&self) -> Vec<&Endpoint> {
+        let now = Instant::now();
+
+        let start = if self.round_robin {
+            self.next.fetch_add(1, Ordering::Relaxed) % self.endpoints.len()
+        } else {
+            0
+        };
+
+        let (mut available, open): (Vec<_>, Vec<_>) = self.endpoints[start..].iter()
+            .chain(&self.endpoints[..start])
+            .partition(|endpoint| endpoint.is_available(now));
+
+        available.extend(open);
+
+        available
+    }
+
+    /// Runs `call` against the endpoints until one answers.
+    ///
+    /// Transport errors and timeouts count as failures of the endpoint and move on to the next
+    /// one. Errors returned by the node itself, like reverts, are passed through as they are.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn call<T, F, Fut>(&self, call: F) -> Result<T, alloy::contract::Error>
+    where
+        F: Fn(ReqwestProvider) -> Fut,
+        Fut: Future<Output = Result<T, alloy::contract::Error>>
+    {
+        let mut last_error = None;
+
+        for endpoint in self.order() {
+            let error = match tokio::time::timeout(self.timeout, call(endpoint.provider.clone())).await {
+                Ok(Err(err)) if is_endpoint_failure(&err) => err,
+                Ok(result) => {
+                    endpoint.record_success();
+                    return result;
+                }
+                Err(_) => TransportErrorKind::custom_str("Request timed out").into()
+            };
+
+            warn!(target: "API", "RPC call to {} on {} failed: {error}", endpoint.url, self.chain);
+
+            endpoint.record_failure();
+            last_error = Some(error);
+        }
+
+        Err(last_error.unwrap_or_else(|| TransportErrorKind::custom_str("No RPC endpoint available").into()))
+    }
+
+    /// Probes every endpoint with `eth_blockNumber`, so open circuits close again once they recover.
+    pub async fn check_health(&self) {
+        for endpoint in &self.endpoints {
+            match tokio::time::timeout(self.timeout, endpoint.provider.get_block_number()).await {
+                Ok(Ok(_)) => endpoint.record_success(),
+                Ok(Err(err)) => {
+                    warn!(target: "API", "Health check of {} on {} failed: {err}", endpoint.url, self.chain);
+                    endpoint.record_failure();
+                }
+                Err(_) => {
+                    warn!(target: "API", "Health check of {} on {} timed out", endpoint.url, self.chain);
+                    endpoint.record_failure();
+                }
+            }
+        }
+    }
+}
+
+fn is_endpoint_failure(error: &alloy::contract::Error) -> bool {
+    match error {
+        alloy::contract::Error::TransportError(RpcError::ErrorResp(_)) => false,
+        alloy::contract::Error::TransportError(_) => true,
+        _ => false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::services::rpc::failover::{Breaker, Endpoints, FAILURE_THRESHOLD, OPEN_DURATION};
+    use crate::supported_networks::SupportedNetworks;
+
+    #[test]
+    fn test_breaker() {
+        let now = Instant::now();
+        let mut breaker = Breaker::default();
+
+        for _ in 0..FAILURE_THRESHOLD {
+            assert!(breaker.is_available(now));
+            breaker.record_failure(now);
+        }
+
+        assert!(!breaker.is_available(now));
+        assert!(breaker.is_available(now + OPEN_DURATION));
+
+        breaker.record_success();
+        assert!(breaker.is_available(now));
+    }
+
+    #[test]
+    fn test_order_skips_open_circuits() {
+        let rpc_urls = ["http://one.invalid".to_string(), "http://two.invalid".to_string(), "http://three.invalid".to_string()];
+        let endpoints = Endpoints::new(SupportedNetworks::Ethereum, &rpc_urls, Duration::from_secs(1), true).unwrap();
+
+        for _ in 0..FAILURE_THRESHOLD {
+            endpoints.endpoints[1].record_failure();
+        }
+
+        let hosts = |endpoints: &Endpoints| -> Vec<String> {
+            endpoints.order().iter().filter_map(|endpoint| endpoint.url.host_str().map(ToString::to_string)).collect()
+        };
+
+        assert_eq!(hosts(&endpoints), vec!["one.invalid", "three.invalid", "two.invalid"]);
+        assert_eq!(hosts(&endpoints), vec!["three.invalid", "one.invalid", "two.invalid"]);
+        assert_eq!(endpoints.preferred_url().host_str(), Some("one.invalid"));
+    }
+}
@@ -1,16 +1,31 @@
-use std::sync::Arc;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::ops::Bound;
+use std::sync::{Arc, LazyLock, Mutex};
 use std::time::Duration;
 
-use alloy::primitives::{Address, FixedBytes, U256};
-use alloy::providers::{ProviderBuilder, ReqwestProvider};
+use alloy::primitives::{Address, Bytes, FixedBytes, B256, U256};
+use alloy::providers::{Provider, ReqwestProvider};
+use alloy::rpc::types::eth::{BlockId, BlockNumberOrTag, Filter};
+use alloy::sol_types::SolEvent;
 use alloy::sol;
+use alloy::transports::RpcError;
+use log::warn;
+use reqwest::{StatusCode, Url};
+use serde::de::DeserializeOwned;
 use thiserror::Error;
+use tokio::task::JoinHandle;
 
-use crate::models::avatar::{AvatarInfo, AvatarInfoWithMetadata, AvatarMetadata};
-use crate::models::nft::NftMetadata;
+use crate::models::avatar::{Avatar, AvatarCollection, AvatarInfo, AvatarInfoWithMetadata, AvatarMetadata, AvatarSource};
+use crate::models::block::BlockRef;
+use crate::models::event::AvatarSetEvent;
+use crate::models::nft::{ContractMetadata, NftInfo, NftMetadata, TokenStandard};
 use crate::services::avatar::AvatarServiceCache;
+use crate::services::signed_avatar::SignedAvatar;
+use crate::services::rpc::failover::Endpoints;
 use crate::supported_networks::SupportedNetworks;
 
+pub mod failover;
 pub mod sepolia;
 pub mod polygon;
 pub mod ethereum;
@@ -23,6 +38,8 @@ const IPFS_GATEWAYS: [&str; 4] = [
     "https://gateway.pinata.cloud/ipfs/",
 ];
 
+const ARWEAVE_GATEWAY: &str = "https://arweave.net/";
+
 sol!(
     #[allow(missing_docs)]
     #[allow(clippy::pub_underscore_fields)]
@@ -31,91 +48,524 @@ sol!(
     "abi/AvatarService.json"
 );
 
+// Seconds before an RPC call is abandoned and the next endpoint is tried
+static RPC_TIMEOUT: LazyLock<Duration> = LazyLock::new(|| {
+    let seconds = std::env::var("RPC_TIMEOUT")
+        .map(|value| value.parse::<u64>().expect("RPC_TIMEOUT must be a number of seconds"))
+        .unwrap_or(5);
+
+    Duration::from_secs(seconds)
+});
+
+// Spread calls over all healthy endpoints instead of preferring the first one
+static RPC_ROUND_ROBIN: LazyLock<bool> = LazyLock::new(|| {
+    std::env::var("RPC_ROUND_ROBIN").is_ok_and(|value| value == "true" || value == "1")
+});
+
+// Seconds between endpoint health checks, 0 disables them
+pub static RPC_HEALTH_CHECK_INTERVAL: LazyLock<Option<Duration>> = LazyLock::new(|| {
+    let seconds = std::env::var("RPC_HEALTH_CHECK_INTERVAL")
+        .map(|value| value.parse::<u64>().expect("RPC_HEALTH_CHECK_INTERVAL must be a number of seconds"))
+        .unwrap_or(30);
+
+    (seconds > 0).then(|| Duration::from_secs(seconds))
+});
+
+// Final blocks kept per network to narrow timestamp lookups
+const MAX_CACHED_BLOCK_TIMES: usize = 10_000;
+
+pub fn client(network: &SupportedNetworks) -> &'static Client {
+    match network {
+        SupportedNetworks::Ethereum => ethereum::client(),
+        SupportedNetworks::Sepolia => sepolia::client(),
+        SupportedNetworks::Polygon => polygon::client(),
+        SupportedNetworks::Base => base::client(),
+    }
+}
+
+/// Reads the comma separated `<PREFIX>_RPC_URLS` in priority order, falling back to `<PREFIX>_RPC_URL`.
+fn rpc_urls(prefix: &str) -> Vec<String> {
+    let value = std::env::var(format!("{prefix}_RPC_URLS"))
+        .or_else(|_| std::env::var(format!("{prefix}_RPC_URL")))
+        .unwrap_or_else(|_| panic!("{prefix}_RPC_URLS not set"));
+
+    value.split(',').map(str::trim).filter(|url| !url.is_empty()).map(ToString::to_string).collect()
+}
+
+/// Reads `<PREFIX>_AVATAR_SERVICE_DEPLOY_BLOCK`, the block event scans start from.
+///
+/// Only event indexing needs it, networks without one are not indexed rather than scanned from genesis.
+fn deploy_block(prefix: &str) -> Option<u64> {
+    std::env::var(format!("{prefix}_AVATAR_SERVICE_DEPLOY_BLOCK")).ok().map(|value| {
+        value.parse::<u64>().unwrap_or_else(|_| panic!("{prefix}_AVATAR_SERVICE_DEPLOY_BLOCK must be a block number"))
+    })
+}
+
+/// Reads `<PREFIX>_CONFIRMATIONS`, the depth after which indexed blocks are considered final.
+fn confirmations(prefix: &str, default: u64) -> u64 {
+    std::env::var(format!("{prefix}_CONFIRMATIONS"))
+        .map(|value| value.parse::<u64>().unwrap_or_else(|_| panic!("{prefix}_CONFIRMATIONS must be a number of blocks")))
+        .unwrap_or(default)
+}
+
+pub fn spawn_health_checks(period: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+
+        loop {
+            interval.tick().await;
+
+            for network in SupportedNetworks::all() {
+                client(&network).endpoints.check_health().await;
+            }
+        }
+    })
+}
+
+/// Historical point to run a lookup at.
+#[derive(Debug, Clone, Copy)]
+pub enum BlockSelector {
+    Number(u64),
+    // Unix timestamp, resolved to the last block mined at or before it
+    Timestamp(u64)
+}
+
+/// Chain data the lookups and the event index read, implemented by `Client` and by fake chains
+/// in tests.
+#[async_trait::async_trait]
+pub trait BlockSource: Send + Sync {
+    async fn get_block(&self, number: BlockNumberOrTag) -> eyre::Result<BlockRef>;
+
+    /// Blocks mined on top of a block before it is treated as final.
+    fn confirmations(&self) -> u64;
+
+    /// Block the avatar service contract was deployed in, where event scans start. Without one
+    /// the network is not indexed.
+    fn deploy_block(&self) -> Option<u64>;
+
+    async fn get_avatar_set_events(&self, from: u64, to: u64) -> eyre::Result<Vec<AvatarSetEvent>>;
+}
+
+/// Final blocks seen by earlier timestamp lookups, keyed by timestamp.
+#[derive(Default)]
+pub struct BlockTimes(Mutex<BTreeMap<u64, BlockRef>>);
+
+impl BlockTimes {
+    /// The closest known blocks at or before and after `timestamp`.
+    fn bounds(&self, timestamp: u64) -> (Option<BlockRef>, Option<BlockRef>) {
+        let blocks = self.0.lock().unwrap();
+
+        let before = blocks.range(..=timestamp).next_back().map(|(_, block)| *block);
+        let after = blocks.range((Bound::Excluded(timestamp), Bound::Unbounded)).next().map(|(_, block)| *block);
+
+        (before, after)
+    }
+
+    fn insert(&self, block: BlockRef) {
+        let mut blocks = self.0.lock().unwrap();
+
+        if blocks.len() >= MAX_CACHED_BLOCK_TIMES {
+            blocks.pop_first();
+        }
+
+        blocks.insert(block.timestamp, block);
+    }
+}
+
+/// Finds the last block mined at or before `timestamp` by binary search over block numbers.
+///
+/// The search starts from the closest final blocks of earlier lookups, so repeated lookups of
+/// nearby times only fetch a few blocks.
+#[allow(clippy::missing_errors_doc)]
+pub async fn find_block_at(source: &dyn BlockSource, block_times: &BlockTimes, timestamp: u64) -> eyre::Result<BlockRef> {
+    let (known_before, known_after) = block_times.bounds(timestamp);
+
+    // Blocks up to `final_to` can't be reorged and are kept for later lookups
+    let (high, final_to) = match known_after {
+        Some(after) => (after.number, after.number),
+        None => {
+            let latest = source.get_block(BlockNumberOrTag::Latest).await?;
+
+            if timestamp >= latest.timestamp {
+                return Ok(latest);
+            }
+
+            (latest.number, latest.number.saturating_sub(source.confirmations()))
+        }
+    };
+
+    let low = match known_before {
+        Some(before) => before,
+        None => {
+            let genesis = source.get_block(BlockNumberOrTag::Earliest).await?;
+
+            if timestamp < genesis.timestamp {
+                return Err(Error::BeforeGenesis.into());
+            }
+
+            block_times.insert(genesis);
+            genesis
+        }
+    };
+
+    // Invariant: block `low` is at or before `timestamp`, block `high` is after it
+    let (mut low, mut high) = (low, high);
+
+    while high - low.number > 1 {
+        let middle = low.number + (high - low.number) / 2;
+        let block = source.get_block(middle.into()).await?;
+
+        if block.number <= final_to {
+            block_times.insert(block);
+        }
+
+        if block.timestamp <= timestamp {
+            low = block;
+        } else {
+            high = middle;
+        }
+    }
+
+    Ok(low)
+}
+
 pub struct Client {
     chain: SupportedNetworks,
-    provider: ReqwestProvider,
-    avatar_service: Address
+    endpoints: Endpoints,
+    avatar_service: Address,
+    deploy_block: Option<u64>,
+    confirmations: u64,
+    block_times: BlockTimes
 }
 
 impl Client {
     #[allow(clippy::missing_errors_doc)]
-    pub fn new(chain: SupportedNetworks, rpc_url: &str, avatar_service: Address) -> eyre::Result<Self> {
-        let provider = ProviderBuilder::new().on_http(rpc_url.parse()?);
+    pub fn new(chain: SupportedNetworks, rpc_urls: &[String], avatar_service: Address) -> eyre::Result<Self> {
+        let endpoints = Endpoints::new(chain.clone(), rpc_urls, *RPC_TIMEOUT, *RPC_ROUND_ROBIN)?;
 
-        Ok(Self { chain, provider, avatar_service })
+        Ok(Self { chain, endpoints, avatar_service, deploy_block: None, confirmations: 0, block_times: BlockTimes::default() })
+    }
+
+    #[must_use]
+    pub fn with_deploy_block(mut self, deploy_block: Option<u64>) -> Self {
+        self.deploy_block = deploy_block;
+        self
+    }
+
+    #[must_use]
+    pub fn with_confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations;
+        self
+    }
+
+    pub fn chain(&self) -> &SupportedNetworks {
+        &self.chain
+    }
+
+    /// URL of the currently preferred RPC endpoint.
+    pub fn rpc_url(&self) -> &Url {
+        self.endpoints.preferred_url()
+    }
+
+    pub fn avatar_service(&self) -> Address {
+        self.avatar_service
+    }
+
+    pub fn deploy_block(&self) -> Option<u64> {
+        self.deploy_block
+    }
+
+    /// Blocks mined on top of a block before its events are treated as final.
+    pub fn confirmations(&self) -> u64 {
+        self.confirmations
+    }
+
+    /// Runs `call` with failover across the network's RPC endpoints.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn call<T, F, Fut>(&self, call: F) -> Result<T, alloy::contract::Error>
+    where
+        F: Fn(ReqwestProvider) -> Fut,
+        Fut: Future<Output = Result<T, alloy::contract::Error>>
+    {
+        self.endpoints.call(call).await
     }
 
     #[allow(clippy::missing_errors_doc)]
-    pub async fn get_avatar_info(&self, address: &Address) -> eyre::Result<AvatarInfo> {
-        let contract = AvatarService::new(self.avatar_service, &self.provider);
+    pub async fn get_avatar_info(&self, address: &Address, block: BlockId, cache: &AvatarServiceCache) -> eyre::Result<AvatarInfo> {
+        let (avatar_service, address) = (self.avatar_service, *address);
+
+        let avatar_info = self.call(|provider| async move {
+            AvatarService::new(avatar_service, provider).getAvatarInfo(address).block(block).call().await.map(|v| v._0)
+        }).await?;
 
-        let avatar_info = contract.getAvatarInfo(*address).call().await?;
+        let mut avatar = Avatar::from(avatar_info.avatar);
+        avatar.asset_id = self.get_asset_id(&avatar, cache).await;
 
-        Ok(AvatarInfo::from(avatar_info._0))
+        Ok(AvatarInfo {
+            chain_id: self.chain.caip2(),
+            account_id: self.chain.caip10(address),
+            avatar,
+            owned: avatar_info.owned,
+            uri: avatar_info.uri,
+            moderated: false,
+            source: AvatarSource::Onchain,
+            confirmed: None,
+        })
     }
 
     #[allow(clippy::missing_errors_doc)]
-    pub async fn get_avatar_info_with_metadata(&self, address: &Address, cache: Arc<AvatarServiceCache>) -> eyre::Result<AvatarInfoWithMetadata> {
-        let avatar_info = self.get_avatar_info(address).await?;
-
-        let nft_metadata = {
-            if avatar_info.avatar.token_address == Address::ZERO {
-                NftMetadata::default()
-            } else {
-                let maybe_cached_token_uri = cache.token_uris.read().await
-                    .get(&self.chain)
-                    .and_then(|map| {
-                        map.get(&(avatar_info.avatar.token_address, avatar_info.avatar.token_id)).cloned()
-                    });
-
-                // Try uri cache first
-                let token_uri = if let Some(uri) = maybe_cached_token_uri {
-                    uri
-                } else {
-                    let uri = self.get_token_uri(&avatar_info.avatar.token_address, avatar_info.avatar.token_id).await?;
-
-                    // Update uri cache
-                    cache.token_uris.write().await
-                        .entry(self.chain.clone())
-                        .or_default()
-                        .insert((avatar_info.avatar.token_address, avatar_info.avatar.token_id), uri.clone());
-
-                    uri
-                };
-
-                let opt_cached_metadata = cache.ipfs.read().await.get(&token_uri).cloned();
-                
-                // Try cache first
-                if let Some(metadata) = opt_cached_metadata { 
-                    metadata
-                } else if let Ok(metadata) = self.get_nft_metadata_from_token_uri(&token_uri).await {
-                    // Cache ipfs result
-                    cache.ipfs.write().await.insert(token_uri, metadata.clone());
-                    metadata
-                } else {
-                    NftMetadata::default()
-                }
-            }
-        };
-        
-        let mut avatar_metadata = AvatarMetadata {
-            image: nft_metadata.image,
-            ..Default::default()
-        };
-        
-        if let Some(network) = cache.verified_collections.read().await.get(&self.chain) {
-            if let Some(collection) = network.get(&avatar_info.avatar.token_address) {
-                avatar_metadata.collection = Some(collection.clone());
+    pub async fn get_avatar_info_with_metadata(&self, address: &Address, block: BlockId, cache: Arc<AvatarServiceCache>) -> eyre::Result<AvatarInfoWithMetadata> {
+        let avatar_info = self.get_avatar_info(address, block, &cache).await?;
+
+        let avatar_metadata = self.get_avatar_metadata(&avatar_info.avatar, &cache).await?;
+
+        Ok(AvatarInfoWithMetadata {
+            chain_id: avatar_info.chain_id,
+            account_id: avatar_info.account_id,
+            avatar: avatar_info.avatar,
+            owned: avatar_info.owned,
+            uri: avatar_info.uri,
+            avatar_metadata,
+            moderated: false,
+            source: AvatarSource::Onchain,
+            confirmed: None,
+        })
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn get_block(&self, number: BlockNumberOrTag) -> eyre::Result<BlockRef> {
+        let block = self.call(|provider| async move {
+            provider.get_block_by_number(number, false).await.map_err(alloy::contract::Error::from)
+        }).await?.ok_or(Error::UnknownBlock)?;
+
+        Ok(BlockRef {
+            number: block.header.number.unwrap_or_default(),
+            hash: block.header.hash.unwrap_or_default(),
+            timestamp: block.header.timestamp,
+        })
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn get_block_number(&self) -> eyre::Result<u64> {
+        let block_number = self.call(|provider| async move {
+            provider.get_block_number().await.map_err(alloy::contract::Error::from)
+        }).await?;
+
+        Ok(block_number)
+    }
+
+    /// Fetches the `AvatarSet` events emitted between `from` and `to` (inclusive), in chain order.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn get_avatar_set_events(&self, from: u64, to: u64) -> eyre::Result<Vec<AvatarSetEvent>> {
+        let filter = Filter::new()
+            .address(self.avatar_service)
+            .event_signature(AvatarService::AvatarSet::SIGNATURE_HASH)
+            .from_block(from)
+            .to_block(to);
+
+        let logs = self.call(|provider| {
+            let filter = filter.clone();
+
+            async move {
+                provider.get_logs(&filter).await.map_err(alloy::contract::Error::from)
             }
+        }).await?;
+
+        let mut events = Vec::with_capacity(logs.len());
+
+        for log in logs.iter().filter(|log| !log.removed) {
+            let decoded = log.log_decode::<AvatarService::AvatarSet>()?;
+            let data = decoded.inner.data;
+
+            events.push(AvatarSetEvent {
+                wallet: data.walletAddress,
+                token_address: data.tokenAddress,
+                token_id: data.tokenId,
+                block_number: log.block_number.unwrap_or_default(),
+                block_hash: log.block_hash.unwrap_or_default(),
+                transaction_hash: log.transaction_hash.unwrap_or_default(),
+                log_index: log.log_index.unwrap_or_default(),
+                timestamp: log.block_timestamp,
+                confirmed: None,
+            });
+        }
+
+        Ok(events)
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn resolve_block(&self, selector: BlockSelector) -> eyre::Result<BlockRef> {
+        match selector {
+            BlockSelector::Number(number) => self.get_block(number.into()).await,
+            BlockSelector::Timestamp(timestamp) => self.get_block_at(timestamp).await
         }
+    }
+
+    async fn get_block_at(&self, timestamp: u64) -> eyre::Result<BlockRef> {
+        find_block_at(self, &self.block_times, timestamp).await
+    }
+
+    /// Resolves an off-chain signed avatar the same way the contract resolves on-chain ones.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn get_signed_avatar_info(&self, address: &Address, signed_avatar: &SignedAvatar, cache: &AvatarServiceCache) -> eyre::Result<AvatarInfo> {
+        let mut avatar = Avatar {
+            token_address: signed_avatar.token_address,
+            token_id: signed_avatar.token_id,
+            asset_id: None,
+        };
+
+        let owned = self.is_owner(address, &avatar.token_address, avatar.token_id).await?;
+        let uri = self.get_cached_token_uri(&avatar.token_address, avatar.token_id, cache).await?;
+        avatar.asset_id = self.get_asset_id(&avatar, cache).await;
+
+        Ok(AvatarInfo {
+            chain_id: self.chain.caip2(),
+            account_id: self.chain.caip10(address),
+            avatar,
+            owned,
+            uri,
+            moderated: false,
+            source: AvatarSource::Signed,
+            confirmed: None,
+        })
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn get_signed_avatar_info_with_metadata(&self, address: &Address, signed_avatar: &SignedAvatar, cache: Arc<AvatarServiceCache>) -> eyre::Result<AvatarInfoWithMetadata> {
+        let avatar_info = self.get_signed_avatar_info(address, signed_avatar, &cache).await?;
+
+        let avatar_metadata = self.get_avatar_metadata(&avatar_info.avatar, &cache).await?;
 
         Ok(AvatarInfoWithMetadata {
+            chain_id: avatar_info.chain_id,
+            account_id: avatar_info.account_id,
             avatar: avatar_info.avatar,
             owned: avatar_info.owned,
             uri: avatar_info.uri,
             avatar_metadata,
+            moderated: false,
+            source: AvatarSource::Signed,
+            confirmed: None,
+        })
+    }
+
+    async fn get_cached_token_uri(&self, token_address: &Address, token_id: U256, cache: &AvatarServiceCache) -> eyre::Result<String> {
+        let maybe_cached_token_uri = cache.token_uris.read().await
+            .get(&self.chain)
+            .and_then(|map| map.get(&(*token_address, token_id)).cloned());
+
+        // Try uri cache first
+        if let Some(uri) = maybe_cached_token_uri {
+            return Ok(uri);
+        }
+
+        let uri = self.get_token_uri(token_address, token_id).await?;
+
+        // Update uri cache
+        cache.token_uris.write().await
+            .entry(self.chain.clone())
+            .or_default()
+            .insert((*token_address, token_id), uri.clone());
+
+        Ok(uri)
+    }
+
+    async fn get_cached_token_standard(&self, token_address: &Address, cache: &AvatarServiceCache) -> eyre::Result<Option<TokenStandard>> {
+        let maybe_cached_standard = cache.token_standards.read().await
+            .get(&self.chain)
+            .and_then(|map| map.get(token_address).copied());
+
+        if maybe_cached_standard.is_some() {
+            return Ok(maybe_cached_standard);
+        }
+
+        let Some(standard) = self.get_token_standard(token_address).await? else {
+            return Ok(None);
+        };
+
+        // Contracts without a standard aren't cached, a proxy may still be upgraded to one
+        cache.token_standards.write().await
+            .entry(self.chain.clone())
+            .or_default()
+            .insert(*token_address, standard);
+
+        Ok(Some(standard))
+    }
+
+    /// CAIP-19 id of the avatar's token, `None` for a cleared avatar or an unknown token standard.
+    async fn get_asset_id(&self, avatar: &Avatar, cache: &AvatarServiceCache) -> Option<String> {
+        if avatar.token_address == Address::ZERO {
+            return None;
+        }
+
+        let standard = self.get_cached_token_standard(&avatar.token_address, cache).await.ok().flatten()?;
+
+        Some(self.chain.caip19(standard, &avatar.token_address, avatar.token_id))
+    }
+
+    /// Resolves a token on its own, through the same cached pipeline as avatars.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn get_nft(&self, token_address: &Address, token_id: U256, cache: &AvatarServiceCache) -> eyre::Result<NftInfo> {
+        let standard = self.get_cached_token_standard(token_address, cache).await?.ok_or(Error::UnsupportedToken)?;
+        let token_uri = self.get_cached_token_uri(token_address, token_id, cache).await?;
+        let metadata = self.get_cached_nft_metadata(&token_uri, cache).await;
+        let collection = self.get_collection(token_address, cache).await;
+
+        Ok(NftInfo {
+            chain_id: self.chain.caip2(),
+            asset_id: self.chain.caip19(standard, token_address, token_id),
+            contract: *token_address,
+            token_id,
+            standard,
+            token_uri,
+            metadata,
+            collection,
+            moderated: false,
         })
     }
+
+    async fn get_avatar_metadata(&self, avatar: &Avatar, cache: &AvatarServiceCache) -> eyre::Result<AvatarMetadata> {
+        if avatar.token_address == Address::ZERO {
+            return Ok(AvatarMetadata::default());
+        }
+
+        let token_uri = self.get_cached_token_uri(&avatar.token_address, avatar.token_id, cache).await?;
+        let nft_metadata = self.get_cached_nft_metadata(&token_uri, cache).await;
+
+        Ok(AvatarMetadata {
+            image: nft_metadata.image,
+            collection: self.get_collection(&avatar.token_address, cache).await,
+        })
+    }
+
+    // Metadata that can't be fetched is left empty rather than failing the lookup
+    async fn get_cached_nft_metadata(&self, token_uri: &str, cache: &AvatarServiceCache) -> NftMetadata {
+        let opt_cached_metadata = cache.ipfs.read().await.get(token_uri).cloned();
+
+        // Try cache first
+        if let Some(metadata) = opt_cached_metadata {
+            metadata
+        } else if let Ok(metadata) = self.get_nft_metadata_from_token_uri(token_uri).await {
+            // Cache ipfs result
+            cache.ipfs.write().await.insert(token_uri.to_string(), metadata.clone());
+            metadata
+        } else {
+            NftMetadata::default()
+        }
+    }
+
+    // The whitelist entry if the collection is verified, otherwise what the contract tells about itself
+    async fn get_collection(&self, token_address: &Address, cache: &AvatarServiceCache) -> Option<AvatarCollection> {
+        let verified_collection = cache.verified_collections.read().await
+            .get(&self.chain)
+            .and_then(|network| network.get(token_address).cloned());
+
+        if verified_collection.is_some() {
+            return verified_collection;
+        }
+
+        self.get_cached_collection_info(token_address, cache).await
+    }
 }
 
 sol!(
@@ -145,6 +595,14 @@ sol!(
         "payable": false,
         "stateMutability": "view",
         "type": "function"
+    }, {
+        "constant": true,
+        "inputs": [{"name": "tokenId", "type": "uint256"}],
+        "name": "ownerOf",
+        "outputs": [{"name": "", "type": "address"}],
+        "payable": false,
+        "stateMutability": "view",
+        "type": "function"
     }]"#
 );
 
@@ -160,6 +618,76 @@ sol!(
         "payable": false,
         "stateMutability": "view",
         "type": "function"
+    }, {
+        "constant": true,
+        "inputs": [{"name": "_owner", "type": "address"}, {"name": "_id", "type": "uint256"}],
+        "name": "balanceOf",
+        "outputs": [{"name": "", "type": "uint256"}],
+        "payable": false,
+        "stateMutability": "view",
+        "type": "function"
+    }]"#
+);
+
+sol!(
+    #[allow(clippy::pub_underscore_fields)]
+    #[sol(rpc)]
+    ERC721Metadata,
+    r#"[{
+        "constant": true,
+        "inputs": [],
+        "name": "name",
+        "outputs": [{"name": "", "type": "string"}],
+        "payable": false,
+        "stateMutability": "view",
+        "type": "function"
+    }, {
+        "constant": true,
+        "inputs": [],
+        "name": "symbol",
+        "outputs": [{"name": "", "type": "string"}],
+        "payable": false,
+        "stateMutability": "view",
+        "type": "function"
+    }]"#
+);
+
+sol!(
+    #[allow(clippy::pub_underscore_fields)]
+    #[sol(rpc)]
+    ERC7572,
+    r#"[{
+        "inputs": [],
+        "name": "contractURI",
+        "outputs": [{"name": "", "type": "string"}],
+        "stateMutability": "view",
+        "type": "function"
+    }]"#
+);
+
+sol!(
+    #[allow(clippy::pub_underscore_fields)]
+    #[sol(rpc)]
+    ERC1271,
+    r#"[{
+        "inputs": [{"name": "hash", "type": "bytes32"}, {"name": "signature", "type": "bytes"}],
+        "name": "isValidSignature",
+        "outputs": [{"name": "magicValue", "type": "bytes4"}],
+        "stateMutability": "view",
+        "type": "function"
+    }]"#
+);
+
+sol!(
+    #[allow(clippy::pub_underscore_fields)]
+    #[sol(rpc)]
+    ERC2771Recipient,
+    r#"[{
+        "inputs": [{"name": "forwarder", "type": "address"}],
+        "name": "isTrustedForwarder",
+        "outputs": [{"name": "", "type": "bool"}],
+        "stateMutability": "view",
+        "type": "function"
     }]"#
 );
 
@@ -168,87 +696,536 @@ pub enum Error {
     #[error("Missing token URI")]
     MissingTokenUri,
     #[error("Empty token URI")]
-    EmptyTokenUri
+    EmptyTokenUri,
+    #[error("Token is neither ERC-721 nor ERC-1155")]
+    UnsupportedToken,
+    #[error("Unknown block")]
+    UnknownBlock,
+    #[error("Timestamp is before the genesis block")]
+    BeforeGenesis,
+    #[error("Metadata URI {0} can't be resolved")]
+    UnresolvableUri(String),
+    #[error("Metadata responded with {0}")]
+    MetadataStatus(StatusCode),
+    #[error("Invalid metadata: {0}")]
+    InvalidMetadata(String)
+}
+
+impl Error {
+    /// Whether a metadata fetch failed for good, a retry would get the same answer.
+    pub fn is_permanent(err: &eyre::Report) -> bool {
+        matches!(err.downcast_ref::<Error>(), Some(Error::UnresolvableUri(_) | Error::MetadataStatus(_) | Error::InvalidMetadata(_)))
+    }
+}
+
+#[async_trait::async_trait]
+impl BlockSource for Client {
+    async fn get_block(&self, number: BlockNumberOrTag) -> eyre::Result<BlockRef> {
+        Client::get_block(self, number).await
+    }
+
+    fn confirmations(&self) -> u64 {
+        self.confirmations
+    }
+
+    fn deploy_block(&self) -> Option<u64> {
+        self.deploy_block
+    }
+
+    async fn get_avatar_set_events(&self, from: u64, to: u64) -> eyre::Result<Vec<AvatarSetEvent>> {
+        Client::get_avatar_set_events(self, from, to).await
+    }
 }
 
 impl Client {
+    /// The standard the contract reports through ERC-165, `None` when it reports neither or
+    /// reverts. Failed calls are errors, they say nothing about the contract.
     #[allow(clippy::missing_errors_doc)]
-    async fn get_token_uri(&self, token_address: &Address, token_id: U256) -> eyre::Result<String> {
+    async fn get_token_standard(&self, token_address: &Address) -> eyre::Result<Option<TokenStandard>> {
         const ERC721_INTERFACE_ID: FixedBytes<4> = FixedBytes::new([0x80, 0xac, 0x58, 0xcd]);
         const ERC1155_INTERFACE_ID: FixedBytes<4> = FixedBytes::new([0xd9, 0xb6, 0x7a, 0x26]);
 
-        let erc165 = ERC165::new(*token_address, &self.provider);
-        let is_erc721 = erc165.supportsInterface(ERC721_INTERFACE_ID).call().await.is_ok_and(|v| v._0);
-        let is_erc1155 = erc165.supportsInterface(ERC1155_INTERFACE_ID).call().await.is_ok_and(|v| v._0);
-
-        if is_erc721 {
-            let erc721 = ERC721::new(*token_address, &self.provider);
-            let token_uri = erc721.tokenURI(token_id).call().await?._0;
-            Ok(token_uri)
-        } else if is_erc1155 {
-            let erc1155 = ERC1155::new(*token_address, &self.provider);
-            let token_uri = erc1155.uri(token_id).call().await?._0;
-            Ok(token_uri)
-        } else {
-            Err(Error::MissingTokenUri.into())
+        let token_address = *token_address;
+
+        let supports_interface = |interface_id: FixedBytes<4>| self.call(move |provider| async move {
+            ERC165::new(token_address, provider).supportsInterface(interface_id).call().await.map(|v| v._0)
+        });
+
+        if definitive(supports_interface(ERC721_INTERFACE_ID).await)?.unwrap_or(false) {
+            return Ok(Some(TokenStandard::Erc721));
+        }
+
+        if definitive(supports_interface(ERC1155_INTERFACE_ID).await)?.unwrap_or(false) {
+            return Ok(Some(TokenStandard::Erc1155));
         }
+
+        Ok(None)
     }
 
     #[allow(clippy::missing_errors_doc)]
-    async fn get_nft_metadata_from_token_uri(&self, token_uri: &str) -> eyre::Result<NftMetadata> {
-        const MAX_RETRIES: usize = 3;
+    async fn get_token_uri(&self, token_address: &Address, token_id: U256) -> eyre::Result<String> {
+        let token_address = *token_address;
+
+        match self.get_token_standard(&token_address).await? {
+            Some(TokenStandard::Erc721) => {
+                let token_uri = self.call(|provider| async move {
+                    ERC721::new(token_address, provider).tokenURI(token_id).call().await.map(|v| v._0)
+                }).await?;
+                Ok(token_uri)
+            }
+            Some(TokenStandard::Erc1155) => {
+                let token_uri = self.call(|provider| async move {
+                    ERC1155::new(token_address, provider).uri(token_id).call().await.map(|v| v._0)
+                }).await?;
+                Ok(token_uri)
+            }
+            None => Err(Error::MissingTokenUri.into())
+        }
+    }
+
+    /// Checks `ownerOf` for ERC-721 and `balanceOf` for ERC-1155 tokens.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn is_owner(&self, wallet: &Address, token_address: &Address, token_id: U256) -> eyre::Result<bool> {
+        let (wallet, token_address) = (*wallet, *token_address);
+
+        match self.get_token_standard(&token_address).await? {
+            Some(TokenStandard::Erc721) => {
+                let owner = self.call(|provider| async move {
+                    ERC721::new(token_address, provider).ownerOf(token_id).call().await.map(|v| v._0)
+                }).await?;
+                Ok(owner == wallet)
+            }
+            Some(TokenStandard::Erc1155) => {
+                let balance = self.call(|provider| async move {
+                    ERC1155::new(token_address, provider).balanceOf(wallet, token_id).call().await.map(|v| v._0)
+                }).await?;
+                Ok(balance > U256::ZERO)
+            }
+            None => Err(Error::UnsupportedToken.into())
+        }
+    }
+
+    /// Whether the AvatarService contract accepts ERC-2771 calls forwarded by `forwarder`,
+    /// contracts without ERC-2771 support don't.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn is_trusted_forwarder(&self, forwarder: &Address) -> eyre::Result<bool> {
+        let (avatar_service, forwarder) = (self.avatar_service, *forwarder);
+
+        let trusted = definitive(self.call(|provider| async move {
+            ERC2771Recipient::new(avatar_service, provider).isTrustedForwarder(forwarder).call().await.map(|v| v._0)
+        }).await)?;
+
+        Ok(trusted.unwrap_or(false))
+    }
+
+    /// Broadcasts a signed transaction with failover, a node that already knows it has accepted it
+    /// through an earlier endpoint.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn send_raw_transaction(&self, encoded: Bytes) -> eyre::Result<()> {
+        let result = self.call(|provider| {
+            let encoded = encoded.clone();
+
+            async move {
+                provider.send_raw_transaction(&encoded).await.map(|_| ()).map_err(alloy::contract::Error::from)
+            }
+        }).await;
 
-        let mut retries = 0;
-        
+        match result {
+            Err(alloy::contract::Error::TransportError(RpcError::ErrorResp(payload))) if payload.message.to_lowercase().contains("already known") => Ok(()),
+            result => Ok(result?)
+        }
+    }
+
+    /// Checks a smart contract wallet signature through EIP-1271 `isValidSignature`.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn is_valid_signature(&self, wallet: &Address, hash: B256, signature: Bytes) -> eyre::Result<bool> {
+        const ERC1271_MAGIC_VALUE: FixedBytes<4> = FixedBytes::new([0x16, 0x26, 0xba, 0x7e]);
+
+        let wallet = *wallet;
+
+        let magic_value = self.call(|provider| {
+            let signature = signature.clone();
+
+            async move {
+                ERC1271::new(wallet, provider).isValidSignature(hash, signature).call().await.map(|v| v.magicValue)
+            }
+        }).await?;
+
+        Ok(magic_value == ERC1271_MAGIC_VALUE)
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    async fn get_nft_metadata_from_token_uri(&self, token_uri: &str) -> eyre::Result<NftMetadata> {
         if token_uri.is_empty() {
             return Err(Error::EmptyTokenUri.into());
         }
 
-        let reqwest_client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(2))
-            .build()
-            .unwrap();
-
-        let nft_metadata = loop {
-            let gateway = IPFS_GATEWAYS[retries % IPFS_GATEWAYS.len()];
-            let token_uri = token_uri.replace("ipfs://", "");
-            let url = format!("{gateway}{token_uri}");
-
-            match reqwest_client.get(&url).send().await {
-                Ok(response) => match response.json::<NftMetadata>().await {
-                    Ok(metadata) => break Ok(metadata),
-                    Err(error) => {
-                        retries += 1;
-                        if retries >= MAX_RETRIES {
-                            break Err(error);
-                        }
-                    }
-                },
-                Err(error) => {
-                    retries += 1;
-                    if retries >= MAX_RETRIES {
-                        break Err(error);
-                    }
+        fetch_json::<NftMetadata>(token_uri).await
+    }
+
+    async fn get_cached_collection_info(&self, token_address: &Address, cache: &AvatarServiceCache) -> Option<AvatarCollection> {
+        let maybe_cached_collection = cache.collections.read().await
+            .get(&self.chain)
+            .and_then(|map| map.get(token_address).cloned());
+
+        if let Some(collection) = maybe_cached_collection {
+            return collection;
+        }
+
+        let collection = match self.get_collection_info(token_address).await {
+            Ok(collection) => collection,
+            Err(err) => {
+                // Not cached, the next lookup asks the contract again
+                warn!(target: "API", "Failed to fetch collection info of {token_address} on {}: {err}", self.chain);
+                return None;
+            }
+        };
+
+        // Contracts without metadata are cached as well, so they are not queried on every lookup
+        cache.collections.write().await
+            .entry(self.chain.clone())
+            .or_default()
+            .insert(*token_address, collection.clone());
+
+        collection
+    }
+
+    /// Builds an unverified collection from the contract's `name()`/`symbol()` and its ERC-7572 `contractURI()`.
+    ///
+    /// Functions the contract doesn't implement are left out, `Err` means the contract couldn't be
+    /// asked and the answer is unknown.
+    async fn get_collection_info(&self, token_address: &Address) -> eyre::Result<Option<AvatarCollection>> {
+        let token_address = *token_address;
+
+        let name = definitive(self.call(|provider| async move {
+            ERC721Metadata::new(token_address, provider).name().call().await.map(|v| v._0)
+        }).await)?.filter(|v| !v.is_empty());
+
+        let symbol = definitive(self.call(|provider| async move {
+            ERC721Metadata::new(token_address, provider).symbol().call().await.map(|v| v._0)
+        }).await)?.filter(|v| !v.is_empty());
+
+        let contract_uri = definitive(self.call(|provider| async move {
+            ERC7572::new(token_address, provider).contractURI().call().await.map(|v| v._0)
+        }).await)?.filter(|v| !v.is_empty());
+
+        let contract_metadata = match contract_uri {
+            None => None,
+            Some(contract_uri) => match fetch_json::<ContractMetadata>(&contract_uri).await {
+                Ok(contract_metadata) => Some(contract_metadata),
+                // Broken metadata stays broken, the collection is cached without it
+                Err(err) if Error::is_permanent(&err) => {
+                    warn!(target: "API", "Ignoring contractURI of {token_address} on {}: {err}", self.chain);
+                    None
                 }
+                Err(err) => return Err(err)
             }
-        }?;
+        };
+
+        if name.is_none() && symbol.is_none() && contract_metadata.is_none() {
+            return Ok(None);
+        }
+
+        let contract_metadata = contract_metadata.unwrap_or_default();
+
+        Ok(Some(AvatarCollection {
+            name: name.or(contract_metadata.name),
+            symbol,
+            description: contract_metadata.description,
+            image: contract_metadata.image,
+            website: contract_metadata.external_link,
+            verified: false,
+            ..Default::default()
+        }))
+    }
+}
+
+/// Whether the node answered the call with a revert, or with no data for an account without
+/// the function, rather than failing to answer it.
+pub fn is_revert(error: &alloy::contract::Error) -> bool {
+    match error {
+        // Code 3 carries revert data, nodes report reverts without a reason under other codes
+        alloy::contract::Error::TransportError(RpcError::ErrorResp(payload)) => {
+            payload.code == 3 || payload.message.to_lowercase().contains("revert")
+        }
+        alloy::contract::Error::AbiError(_) => true,
+        _ => false
+    }
+}
+
+// A revert is a definitive empty answer, any other failure is passed on
+fn definitive<T>(result: Result<T, alloy::contract::Error>) -> Result<Option<T>, alloy::contract::Error> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(err) if is_revert(&err) => Ok(None),
+        Err(err) => Err(err)
+    }
+}
+
+/// Fetches JSON from an `ipfs://`, `ar://`, `http(s)://` or inline `data:` URI, moving on to
+/// the next IPFS gateway when a request fails.
+///
+/// Answers a retry wouldn't change, an unknown scheme, an error status or a body that isn't the
+/// expected JSON, fail right away with an `Error` that `Error::is_permanent` recognizes.
+async fn fetch_json<T: DeserializeOwned>(uri: &str) -> eyre::Result<T> {
+    const MAX_RETRIES: usize = 3;
+
+    let decode = |body: &[u8]| serde_json::from_slice::<T>(body).map_err(|err| Error::InvalidMetadata(err.to_string()));
+
+    if let Some(data) = uri.strip_prefix("data:") {
+        let body = decode_data_uri(data).ok_or_else(|| Error::InvalidMetadata("Malformed data URI".to_string()))?;
+
+        return Ok(decode(&body)?);
+    }
+
+    let mut retries = 0;
+
+    let reqwest_client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(2))
+        .build()
+        .unwrap();
+
+    loop {
+        let url = resolve_uri(uri, IPFS_GATEWAYS[retries % IPFS_GATEWAYS.len()]).ok_or_else(|| Error::UnresolvableUri(uri.to_string()))?;
+
+        let error = match reqwest_client.get(&url).send().await {
+            // Overloaded gateways and rate limits pass
+            Ok(response) if response.status().is_server_error() || matches!(response.status(), StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_MANY_REQUESTS) => {
+                eyre::eyre!("Metadata responded with {}", response.status())
+            }
+            Ok(response) if !response.status().is_success() => return Err(Error::MetadataStatus(response.status()).into()),
+            Ok(response) => match response.bytes().await {
+                Ok(body) => return Ok(decode(&body)?),
+                Err(err) => err.into()
+            },
+            Err(err) => err.into()
+        };
+
+        retries += 1;
+
+        if retries >= MAX_RETRIES {
+            return Err(error);
+        }
+    }
+}
+
+/// Rewrites `ipfs://` URIs and bare CIDs to the given gateway and `ar://` URIs to Arweave's,
+/// `http(s)://` URIs are fetched as they are. Other schemes, `data:` among them, have no URL.
+fn resolve_uri(uri: &str, gateway: &str) -> Option<String> {
+    if let Some(path) = uri.strip_prefix("ipfs://") {
+        Some(format!("{gateway}{}", path.trim_start_matches("ipfs/")))
+    } else if let Some(path) = uri.strip_prefix("ar://") {
+        Some(format!("{ARWEAVE_GATEWAY}{path}"))
+    } else if uri.starts_with("http://") || uri.starts_with("https://") {
+        Some(uri.to_string())
+    } else if has_scheme(uri) {
+        None
+    } else {
+        Some(format!("{gateway}{uri}"))
+    }
+}
+
+// Whether the URI starts with a scheme, e.g. `data:` or `ftp:`
+fn has_scheme(uri: &str) -> bool {
+    uri.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    })
+}
+
+/// HTTP URL an image URI can be fetched from by any client, `ipfs://` and `ar://` are rewritten
+/// to public gateways. Inline `data:` images and unknown schemes have none.
+pub fn http_url(uri: &str) -> Option<String> {
+    if uri.starts_with("ipfs://") || uri.starts_with("ar://") || uri.starts_with("http://") || uri.starts_with("https://") {
+        resolve_uri(uri, IPFS_GATEWAYS[0])
+    } else {
+        None
+    }
+}
+
+/// Payload of a `data:` URI given without its scheme, either base64 or percent-encoded.
+fn decode_data_uri(data: &str) -> Option<Vec<u8>> {
+    let (media_type, payload) = data.split_once(',')?;
+
+    if media_type.split(';').any(|parameter| parameter.trim().eq_ignore_ascii_case("base64")) {
+        decode_base64(payload)
+    } else {
+        decode_percent(payload)
+    }
+}
+
+// Standard and URL-safe alphabets, padding is optional
+fn decode_base64(payload: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(payload.len() * 3 / 4);
+    let (mut buffer, mut bits) = (0u32, 0u32);
+
+    for byte in payload.bytes().filter(|byte| !byte.is_ascii_whitespace()) {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            _ => return None
+        };
+
+        buffer = (buffer << 6) | u32::from(value);
+        bits += 6;
 
-        Ok(nft_metadata)
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
     }
+
+    Some(decoded)
+}
+
+fn decode_percent(payload: &str) -> Option<Vec<u8>> {
+    let bytes = payload.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut position = 0;
+
+    while position < bytes.len() {
+        if bytes[position] == b'%' {
+            let hex = payload.get(position + 1..position + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            position += 3;
+        } else {
+            decoded.push(bytes[position]);
+            position += 1;
+        }
+    }
+
+    Some(decoded)
 }
 
 #[cfg(test)]
 mod tests {
-    use alloy::primitives::{address, U256};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use alloy::primitives::{address, B256, U256};
+    use alloy::rpc::types::eth::BlockNumberOrTag;
     use dotenv::dotenv;
 
-    use crate::services::rpc::polygon;
+    use crate::models::block::BlockRef;
+    use crate::models::event::AvatarSetEvent;
+    use crate::models::nft::ContractMetadata;
+    use crate::services::rpc::{fetch_json, find_block_at, http_url, polygon, resolve_uri, BlockSource, BlockTimes, Error};
+
+    // Blocks 0..=100 mined every 12 seconds from timestamp 1000
+    struct FakeChain {
+        fetched: AtomicUsize
+    }
+
+    #[async_trait::async_trait]
+    impl BlockSource for FakeChain {
+        async fn get_block(&self, number: BlockNumberOrTag) -> eyre::Result<BlockRef> {
+            self.fetched.fetch_add(1, Ordering::Relaxed);
+
+            let number = match number {
+                BlockNumberOrTag::Latest => 100,
+                BlockNumberOrTag::Earliest => 0,
+                BlockNumberOrTag::Number(number) if number <= 100 => number,
+                _ => return Err(Error::UnknownBlock.into())
+            };
+
+            Ok(BlockRef { number, hash: B256::with_last_byte(u8::try_from(number).unwrap()), timestamp: 1000 + number * 12 })
+        }
+
+        fn confirmations(&self) -> u64 {
+            10
+        }
+
+        fn deploy_block(&self) -> Option<u64> {
+            Some(0)
+        }
+
+        async fn get_avatar_set_events(&self, _from: u64, _to: u64) -> eyre::Result<Vec<AvatarSetEvent>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_find_block_at() {
+        let chain = FakeChain { fetched: AtomicUsize::new(0) };
+        let block_times = BlockTimes::default();
+
+        assert_eq!(find_block_at(&chain, &block_times, 1000).await.unwrap().number, 0);
+        assert_eq!(find_block_at(&chain, &block_times, 1000 + 50 * 12 + 11).await.unwrap().number, 50);
+        assert_eq!(find_block_at(&chain, &block_times, 1000 + 51 * 12).await.unwrap().number, 51);
+        assert_eq!(find_block_at(&chain, &block_times, u64::MAX).await.unwrap().number, 100);
+
+        let error = find_block_at(&chain, &block_times, 999).await.unwrap_err();
+        assert!(matches!(error.downcast_ref::<Error>(), Some(Error::BeforeGenesis)));
+    }
+
+    #[tokio::test]
+    async fn test_find_block_at_reuses_final_blocks() {
+        let chain = FakeChain { fetched: AtomicUsize::new(0) };
+        let block_times = BlockTimes::default();
+
+        assert_eq!(find_block_at(&chain, &block_times, 1000 + 30 * 12).await.unwrap().number, 30);
+        let first = chain.fetched.swap(0, Ordering::Relaxed);
+
+        // Known final blocks around the time bound the search, the latest block isn't needed
+        assert_eq!(find_block_at(&chain, &block_times, 1000 + 31 * 12).await.unwrap().number, 31);
+        assert!(chain.fetched.load(Ordering::Relaxed) < first);
+
+        // Blocks within the confirmation depth of the tip are not kept
+        assert!(block_times.bounds(1000 + 95 * 12).0.is_none_or(|block| block.number <= 90));
+    }
+
+    #[test]
+    fn test_resolve_uri() {
+        let gateway = "https://ipfs.io/ipfs/";
+
+        assert_eq!(resolve_uri("ipfs://QmHash/1.json", gateway).as_deref(), Some("https://ipfs.io/ipfs/QmHash/1.json"));
+        assert_eq!(resolve_uri("ipfs://ipfs/QmHash/1.json", gateway).as_deref(), Some("https://ipfs.io/ipfs/QmHash/1.json"));
+        assert_eq!(resolve_uri("https://example.com/contract.json", gateway).as_deref(), Some("https://example.com/contract.json"));
+        assert_eq!(resolve_uri("QmHash/1.json", gateway).as_deref(), Some("https://ipfs.io/ipfs/QmHash/1.json"));
+
+        // Unknown schemes are never sent to the gateway
+        assert_eq!(resolve_uri("data:application/json,{}", gateway), None);
+        assert_eq!(resolve_uri("ftp://example.com/contract.json", gateway), None);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_json_decodes_data_uris() {
+        // {"name":"Onchain","description":"Stored in the contract"}
+        let base64 = "data:application/json;base64,eyJuYW1lIjoiT25jaGFpbiIsImRlc2NyaXB0aW9uIjoiU3RvcmVkIGluIHRoZSBjb250cmFjdCJ9";
+        let metadata = fetch_json::<ContractMetadata>(base64).await.unwrap();
+        assert_eq!(metadata.name.as_deref(), Some("Onchain"));
+        assert_eq!(metadata.description.as_deref(), Some("Stored in the contract"));
+
+        let percent = "data:application/json;charset=utf-8,%7B%22name%22%3A%22Percent%20encoded%22%7D";
+        assert_eq!(fetch_json::<ContractMetadata>(percent).await.unwrap().name.as_deref(), Some("Percent encoded"));
+
+        let plain = r#"data:application/json,{"name":"Plain"}"#;
+        assert_eq!(fetch_json::<ContractMetadata>(plain).await.unwrap().name.as_deref(), Some("Plain"));
+
+        // Broken inline metadata and unknown schemes fail for good, without a request
+        for uri in ["data:application/json;base64,e30*", "data:application/json,not json", "ftp://example.com/contract.json"] {
+            let error = fetch_json::<ContractMetadata>(uri).await.unwrap_err();
+            assert!(Error::is_permanent(&error), "{uri}");
+        }
+    }
+
+    #[test]
+    fn test_http_url() {
+        assert_eq!(http_url("ipfs://ipfs/QmHash/1.png"), Some("https://ipfs.io/ipfs/QmHash/1.png".to_string()));
+        assert_eq!(http_url("ar://TxId"), Some("https://arweave.net/TxId".to_string()));
+        assert_eq!(http_url("https://example.com/1.png"), Some("https://example.com/1.png".to_string()));
+        assert_eq!(http_url("data:image/svg+xml;base64,PHN2Zz4="), None);
+    }
 
     #[tokio::test]
     async fn test_get_token_uri() {
         dotenv().ok();
 
-        let client = polygon::new();
+        let client = polygon::client();
 
         let token_address = address!("907808732079863886443057C65827a0F1c64357");
         let token_id = U256::from(1);
@@ -262,7 +1239,7 @@ mod tests {
     async fn test_get_metadata_from_token_uri() {
         dotenv().ok();
 
-        let client = polygon::new();
+        let client = polygon::client();
 
         let token_uri = "ipfs://QmNfoE5tQaBGiXSNdyRDresLC27QCHNwP75zwuXfntdBmM/1.json";
 
@@ -2,19 +2,23 @@ use std::sync::LazyLock;
 
 use alloy::primitives::Address;
 
-use crate::services::rpc::Client;
+use crate::services::rpc::{confirmations, deploy_block, rpc_urls, Client};
 use crate::supported_networks::SupportedNetworks;
 
-static SEPOLIA_RPC_URL: LazyLock<String> = LazyLock::new(|| {
-    std::env::var("SEPOLIA_RPC_URL").expect("SEPOLIA_RPC_URL not set")
-});
+static SEPOLIA_RPC_URLS: LazyLock<Vec<String>> = LazyLock::new(|| rpc_urls("SEPOLIA"));
 
 static SEPOLIA_AVATAR_SERVICE: LazyLock<String> = LazyLock::new(|| {
     std::env::var("SEPOLIA_AVATAR_SERVICE").expect("SEPOLIA_AVATAR_SERVICE not set")
 });
 
-pub fn new() -> Client {
+static CLIENT: LazyLock<Client> = LazyLock::new(|| {
     let contract_address = SEPOLIA_AVATAR_SERVICE.parse::<Address>().unwrap();
-    
-    Client::new(SupportedNetworks::Sepolia, &SEPOLIA_RPC_URL, contract_address).unwrap()
-}
\ No newline at end of file
+
+    Client::new(SupportedNetworks::Sepolia, &SEPOLIA_RPC_URLS, contract_address).unwrap()
+        .with_deploy_block(deploy_block("SEPOLIA"))
+        .with_confirmations(confirmations("SEPOLIA", 12))
+});
+
+pub fn client() -> &'static Client {
+    &CLIENT
+}
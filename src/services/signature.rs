@@ -0,0 +1,25 @@
+use alloy::primitives::{Address, Bytes, Signature, B256};
+
+use crate::services::rpc::{self, Client};
+use crate::supported_networks::SupportedNetworks;
+
+/// Checks that `signature` over `hash` was produced by `address`.
+///
+/// EOAs are checked through ecrecover, anything else falls back to EIP-1271 on `network` so
+/// smart contract wallets can sign as well.
+pub async fn verify(network: &SupportedNetworks, address: &Address, hash: B256, signature: &Bytes) -> bool {
+    verify_with(rpc::client(network), address, hash, signature).await
+}
+
+/// Same as [`verify`], with the EIP-1271 fallback going through `client`.
+pub async fn verify_with(client: &Client, address: &Address, hash: B256, signature: &Bytes) -> bool {
+    let maybe_signer = Signature::try_from(signature.as_ref())
+        .ok()
+        .and_then(|signature| signature.recover_address_from_prehash(&hash).ok());
+
+    if maybe_signer == Some(*address) {
+        return true;
+    }
+
+    client.is_valid_signature(address, hash, signature.clone()).await.unwrap_or(false)
+}
@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+
+use alloy::primitives::{Address, Bytes, U256};
+use alloy::sol;
+use alloy::sol_types::{eip712_domain, Eip712Domain, SolStruct};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+use crate::services::rpc::Client;
+use crate::services::store::Store;
+use crate::services::{rpc, signature};
+use crate::supported_networks::SupportedNetworks;
+
+sol! {
+    struct SetAvatar {
+        address wallet;
+        uint256 chainId;
+        address tokenAddress;
+        uint256 tokenId;
+        uint256 nonce;
+        uint256 expiry;
+    }
+}
+
+// Chain agnostic, the target network is part of the signed message
+pub const DOMAIN: Eip712Domain = eip712_domain! {
+    name: "Ethereum Avatar Service",
+    version: "1",
+};
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Unsupported chain id {0}")]
+    UnsupportedChain(U256),
+    #[error("Signed avatar has expired")]
+    Expired,
+    #[error("Nonce must be greater than the last accepted nonce")]
+    StaleNonce,
+    #[error("Invalid signature")]
+    InvalidSignature,
+    #[error("Wallet does not own the token")]
+    NotOwner,
+    #[error("Failed to check token ownership: {0}")]
+    Ownership(eyre::Report),
+    #[error("Failed to persist signed avatar: {0}")]
+    Persist(eyre::Report)
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct SignedAvatar {
+    pub token_address: Address,
+    pub token_id: U256,
+    pub nonce: U256,
+    // Unix timestamp after which the avatar is no longer served
+    pub expiry: u64,
+    pub signature: Bytes
+}
+
+type SignedAvatars = HashMap<(SupportedNetworks, Address), SignedAvatar>;
+
+#[derive(Deserialize, Serialize)]
+struct StoredSignedAvatar {
+    network: SupportedNetworks,
+    wallet: Address,
+    #[serde(flatten)]
+    signed_avatar: SignedAvatar
+}
+
+/// Avatars set off-chain through EIP-712 signed `SetAvatar` messages, served when a wallet has no
+/// on-chain avatar.
+///
+/// Expired avatars are kept, their nonce stays the high-water mark of the wallet so old messages
+/// can't be replayed.
+#[derive(Default)]
+pub struct SignedAvatarService {
+    avatars: RwLock<SignedAvatars>,
+    store: Option<Store>
+}
+
+fn now() -> u64 {
+    u64::try_from(Utc::now().timestamp()).unwrap_or_default()
+}
+
+impl SignedAvatarService {
+    /// Restores the signed avatars saved in `store`.
+    #[allow(clippy::missing_errors_doc)]
+    pub fn load(store: Store) -> eyre::Result<Self> {
+        let avatars = store.load::<Vec<StoredSignedAvatar>>()?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|stored| ((stored.network, stored.wallet), stored.signed_avatar))
+            .collect();
+
+        Ok(Self { avatars: RwLock::new(avatars), store: Some(store) })
+    }
+
+    pub async fn get(&self, network: &SupportedNetworks, wallet: &Address) -> Option<SignedAvatar> {
+        self.avatars.read().await
+            .get(&(network.clone(), *wallet))
+            .filter(|signed_avatar| signed_avatar.expiry > now())
+            .cloned()
+    }
+
+    /// Verifies and stores a signed `SetAvatar` message, replacing any earlier one of the wallet.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn submit(&self, message: SetAvatar, signature: Bytes) -> Result<SupportedNetworks, Error> {
+        let network = u64::try_from(message.chainId).ok()
+            .and_then(SupportedNetworks::from_chain_id)
+            .ok_or(Error::UnsupportedChain(message.chainId))?;
+
+        self.submit_with(rpc::client(&network), message, signature).await
+    }
+
+    async fn submit_with(&self, client: &Client, message: SetAvatar, signature: Bytes) -> Result<SupportedNetworks, Error> {
+        let network = client.chain().clone();
+
+        if message.chainId != U256::from(network.chain_id()) {
+            return Err(Error::UnsupportedChain(message.chainId));
+        }
+
+        let expiry = u64::try_from(message.expiry).unwrap_or(u64::MAX);
+
+        if expiry <= now() {
+            return Err(Error::Expired);
+        }
+
+        let key = (network.clone(), message.wallet);
+
+        if self.avatars.read().await.get(&key).is_some_and(|signed_avatar| message.nonce <= signed_avatar.nonce) {
+            return Err(Error::StaleNonce);
+        }
+
+        let hash = message.eip712_signing_hash(&DOMAIN);
+
+        if !signature::verify_with(client, &message.wallet, hash, &signature).await {
+            return Err(Error::InvalidSignature);
+        }
+
+        let owned = client
+            .is_owner(&message.wallet, &message.tokenAddress, message.tokenId).await
+            .map_err(Error::Ownership)?;
+
+        if !owned {
+            return Err(Error::NotOwner);
+        }
+
+        let mut avatars = self.avatars.write().await;
+
+        // Checked again, another request may have been accepted in the meantime
+        if avatars.get(&key).is_some_and(|signed_avatar| message.nonce <= signed_avatar.nonce) {
+            return Err(Error::StaleNonce);
+        }
+
+        let signed_avatar = SignedAvatar {
+            token_address: message.tokenAddress,
+            token_id: message.tokenId,
+            nonce: message.nonce,
+            expiry,
+            signature,
+        };
+
+        // Saved before it is served, an avatar lost on restart would let its nonce be replayed
+        self.persist(&avatars, &key, &signed_avatar).await?;
+
+        avatars.insert(key, signed_avatar);
+
+        Ok(network)
+    }
+
+    async fn persist(&self, avatars: &SignedAvatars, key: &(SupportedNetworks, Address), signed_avatar: &SignedAvatar) -> Result<(), Error> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+
+        let stored: Vec<StoredSignedAvatar> = avatars.iter()
+            .filter(|(other, _)| *other != key)
+            .chain([(key, signed_avatar)])
+            .map(|((network, wallet), signed_avatar)| StoredSignedAvatar {
+                network: network.clone(),
+                wallet: *wallet,
+                signed_avatar: signed_avatar.clone(),
+            })
+            .collect();
+
+        store.save(&stored).await.map_err(Error::Persist)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{address, b256, Address, Bytes, U256};
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::signers::SignerSync;
+    use alloy::sol_types::SolStruct;
+
+    use crate::services::rpc::Client;
+    use crate::services::signed_avatar::{now, Error, SetAvatar, SignedAvatar, SignedAvatarService, DOMAIN};
+    use crate::services::store::Store;
+    use crate::supported_networks::SupportedNetworks;
+
+    const WALLET: Address = address!("1111111111111111111111111111111111111111");
+    const TOKEN: Address = address!("2222222222222222222222222222222222222222");
+
+    fn message(nonce: u64, expiry: u64) -> SetAvatar {
+        SetAvatar {
+            wallet: WALLET,
+            chainId: U256::from(1),
+            tokenAddress: TOKEN,
+            tokenId: U256::from(42),
+            nonce: U256::from(nonce),
+            expiry: U256::from(expiry),
+        }
+    }
+
+    async fn with_nonce(nonce: u64) -> SignedAvatarService {
+        let service = SignedAvatarService::default();
+
+        service.avatars.write().await.insert((SupportedNetworks::Ethereum, WALLET), SignedAvatar {
+            token_address: TOKEN,
+            token_id: U256::from(42),
+            nonce: U256::from(nonce),
+            expiry: now() - 1,
+            signature: Bytes::new(),
+        });
+
+        service
+    }
+
+    #[test]
+    fn test_signing_hash() {
+        assert_eq!(DOMAIN.separator(), b256!("444d486b76cfb7ecdf367b5e13cf5d1126d0ff181d16c9edac27e404d8e726d5"));
+        assert_eq!(message(7, 2_000_000_000).eip712_signing_hash(&DOMAIN), b256!("65cedf5b91e31f3183d9f5a4f6fd566fea0a4fffcbbf36db31b85fa4b8870f9c"));
+    }
+
+    #[tokio::test]
+    async fn test_reject_expired() {
+        let result = SignedAvatarService::default().submit(message(1, now()), Bytes::new()).await;
+
+        assert!(matches!(result, Err(Error::Expired)));
+    }
+
+    #[tokio::test]
+    async fn test_reject_stale_nonce() {
+        // The nonce of an expired avatar still counts
+        let service = with_nonce(5).await;
+
+        for nonce in [4, 5] {
+            let result = service.submit(message(nonce, now() + 60), Bytes::new()).await;
+            assert!(matches!(result, Err(Error::StaleNonce)));
+        }
+
+        assert!(service.get(&SupportedNetworks::Ethereum, &WALLET).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reject_wrong_signer() {
+        // Nothing listens there, the EIP-1271 fallback fails like it would for an EOA
+        let client = Client::new(SupportedNetworks::Ethereum, &["http://127.0.0.1:1".to_string()], Address::ZERO).unwrap();
+
+        let message = message(1, now() + 60);
+        let signature = PrivateKeySigner::random().sign_hash_sync(&message.eip712_signing_hash(&DOMAIN)).unwrap();
+
+        let result = SignedAvatarService::default().submit_with(&client, message, Bytes::from(signature.as_bytes())).await;
+
+        assert!(matches!(result, Err(Error::InvalidSignature)));
+    }
+
+    #[tokio::test]
+    async fn test_nonces_are_persisted() {
+        let dir = std::env::temp_dir().join(format!("eas-api-signed-avatars-{}", rand::random::<u64>()));
+        let store = Store::at(dir.join("signed_avatars.json"));
+
+        let service = SignedAvatarService { store: Some(store.clone()), ..with_nonce(5).await };
+        let signed_avatar = service.avatars.read().await[&(SupportedNetworks::Ethereum, WALLET)].clone();
+        service.persist(&*service.avatars.read().await, &(SupportedNetworks::Ethereum, WALLET), &signed_avatar).await.unwrap();
+
+        let restored = SignedAvatarService::load(store).unwrap();
+        let result = restored.submit(message(5, now() + 60), Bytes::new()).await;
+        assert!(matches!(result, Err(Error::StaleNonce)));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::LazyLock;
+
+use alloy::primitives::{eip191_hash_message, hex, Address, Bytes};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+use crate::services::signature;
+use crate::supported_networks::SupportedNetworks;
+
+// Domain the SIWE messages must be bound to, logins are refused when unset
+pub static SIWE_DOMAIN: LazyLock<Option<String>> = LazyLock::new(|| {
+    std::env::var("SIWE_DOMAIN").ok()
+});
+
+pub static SESSION_TTL: LazyLock<Duration> = LazyLock::new(|| {
+    let seconds = std::env::var("SESSION_TTL")
+        .map(|value| value.parse::<i64>().expect("SESSION_TTL must be a number of seconds"))
+        .unwrap_or(86_400);
+
+    Duration::seconds(seconds)
+});
+
+const NONCE_TTL_SECONDS: i64 = 600;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Sign-In with Ethereum is not configured")]
+    NotConfigured,
+    #[error("Malformed SIWE message: invalid {0}")]
+    Malformed(&'static str),
+    #[error("Message is bound to domain {0}")]
+    DomainMismatch(String),
+    #[error("Unsupported SIWE version {0}")]
+    UnsupportedVersion(String),
+    #[error("Unsupported chain id {0}")]
+    UnsupportedChain(u64),
+    #[error("Unknown or expired nonce")]
+    InvalidNonce,
+    #[error("Message has expired")]
+    Expired,
+    #[error("Message is not valid yet")]
+    NotYetValid,
+    #[error("Invalid signature")]
+    InvalidSignature
+}
+
+/// An EIP-4361 message, resources and request id are accepted but not used.
+#[derive(Debug)]
+pub struct SiweMessage {
+    pub domain: String,
+    pub address: Address,
+    pub statement: Option<String>,
+    pub uri: String,
+    pub version: String,
+    pub chain_id: u64,
+    pub nonce: String,
+    pub issued_at: DateTime<Utc>,
+    pub expiration_time: Option<DateTime<Utc>>,
+    pub not_before: Option<DateTime<Utc>>
+}
+
+impl FromStr for SiweMessage {
+    type Err = Error;
+
+    fn from_str(message: &str) -> Result<Self, Self::Err> {
+        const HEADER_SUFFIX: &str = " wants you to sign in with your Ethereum account:";
+
+        let mut lines = message.lines();
+
+        let domain = lines.next()
+            .and_then(|line| line.strip_suffix(HEADER_SUFFIX))
+            .ok_or(Error::Malformed("header"))?;
+
+        // The scheme is optional and not part of the domain binding
+        let domain = domain.split_once("://").map_or(domain, |(_, domain)| domain);
+
+        let address = lines.next()
+            .and_then(|line| Address::parse_checksummed(line, None).ok())
+            .ok_or(Error::Malformed("address"))?;
+
+        let mut statement = Vec::new();
+        let mut fields = HashMap::new();
+
+        for line in lines {
+            if line == "Resources:" {
+                break;
+            }
+
+            if fields.is_empty() && !line.starts_with("URI: ") {
+                if !line.is_empty() {
+                    statement.push(line);
+                }
+                continue;
+            }
+
+            let (key, value) = line.split_once(": ").ok_or(Error::Malformed("field"))?;
+            fields.insert(key, value);
+        }
+
+        let field = |name: &'static str| fields.get(name).copied().ok_or(Error::Malformed(name));
+
+        let timestamp = |name: &'static str| -> Result<Option<DateTime<Utc>>, Error> {
+            fields.get(name)
+                .map(|value| DateTime::parse_from_rfc3339(value).map(|time| time.with_timezone(&Utc)).map_err(|_| Error::Malformed(name)))
+                .transpose()
+        };
+
+        Ok(Self {
+            domain: domain.to_string(),
+            address,
+            statement: (!statement.is_empty()).then(|| statement.join("\n")),
+            uri: field("URI")?.to_string(),
+            version: field("Version")?.to_string(),
+            chain_id: field("Chain ID")?.parse().map_err(|_| Error::Malformed("Chain ID"))?,
+            nonce: field("Nonce")?.to_string(),
+            issued_at: timestamp("Issued At")?.ok_or(Error::Malformed("Issued At"))?,
+            expiration_time: timestamp("Expiration Time")?,
+            not_before: timestamp("Not Before")?,
+        })
+    }
+}
+
+#[derive(Clone, Serialize)]
+pub struct Session {
+    pub address: Address,
+    pub chain_id: u64,
+    pub expires_at: DateTime<Utc>
+}
+
+/// Issues SIWE nonces and keeps the resulting wallet sessions in memory.
+#[derive(Default)]
+pub struct SessionService {
+    nonces: RwLock<HashMap<String, DateTime<Utc>>>,
+    sessions: RwLock<HashMap<String, Session>>
+}
+
+impl SessionService {
+    pub async fn issue_nonce(&self) -> String {
+        let nonce = hex::encode(rand::random::<[u8; 16]>());
+        let now = Utc::now();
+
+        let mut nonces = self.nonces.write().await;
+        nonces.retain(|_, expires_at| *expires_at > now);
+        nonces.insert(nonce.clone(), now + Duration::seconds(NONCE_TTL_SECONDS));
+
+        nonce
+    }
+
+    /// Verifies a signed SIWE message and opens a session, returning its bearer token.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn sign_in(&self, message: &str, signature: &Bytes) -> Result<(String, Session), Error> {
+        let domain = SIWE_DOMAIN.as_deref().ok_or(Error::NotConfigured)?;
+
+        let siwe_message = message.parse::<SiweMessage>()?;
+
+        if siwe_message.domain != domain {
+            return Err(Error::DomainMismatch(siwe_message.domain));
+        }
+
+        if siwe_message.version != "1" {
+            return Err(Error::UnsupportedVersion(siwe_message.version));
+        }
+
+        let network = SupportedNetworks::from_chain_id(siwe_message.chain_id)
+            .ok_or(Error::UnsupportedChain(siwe_message.chain_id))?;
+
+        let now = Utc::now();
+
+        if siwe_message.expiration_time.is_some_and(|expiration_time| expiration_time <= now) {
+            return Err(Error::Expired);
+        }
+
+        if siwe_message.not_before.is_some_and(|not_before| not_before > now) {
+            return Err(Error::NotYetValid);
+        }
+
+        // Nonces are single use, even when the signature turns out to be invalid
+        let nonce_valid = self.nonces.write().await
+            .remove(&siwe_message.nonce)
+            .is_some_and(|expires_at| expires_at > now);
+
+        if !nonce_valid {
+            return Err(Error::InvalidNonce);
+        }
+
+        if !signature::verify(&network, &siwe_message.address, eip191_hash_message(message), signature).await {
+            return Err(Error::InvalidSignature);
+        }
+
+        let session_expires_at = now + *SESSION_TTL;
+
+        let session = Session {
+            address: siwe_message.address,
+            chain_id: siwe_message.chain_id,
+            expires_at: siwe_message.expiration_time.map_or(session_expires_at, |expiration_time| expiration_time.min(session_expires_at)),
+        };
+
+        let token = hex::encode(rand::random::<[u8; 32]>());
+
+        let mut sessions = self.sessions.write().await;
+        sessions.retain(|_, session| session.expires_at > now);
+        sessions.insert(token.clone(), session.clone());
+
+        Ok((token, session))
+    }
+
+    pub async fn session(&self, token: &str) -> Option<Session> {
+        self.sessions.read().await
+            .get(token)
+            .filter(|session| session.expires_at > Utc::now())
+            .cloned()
+    }
+
+    pub async fn sign_out(&self, token: &str) {
+        self.sessions.write().await.remove(token);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::address;
+
+    use crate::services::siwe::SiweMessage;
+
+    #[test]
+    fn test_parse_message() {
+        let message = "example.com wants you to sign in with your Ethereum account:\n\
+            0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2\n\
+            \n\
+            Sign in to the Ethereum Avatar Service.\n\
+            \n\
+            URI: https://example.com/login\n\
+            Version: 1\n\
+            Chain ID: 137\n\
+            Nonce: 32891756a1b2c3d4\n\
+            Issued At: 2024-06-01T12:00:00Z\n\
+            Expiration Time: 2024-06-02T12:00:00.000Z\n\
+            Resources:\n\
+            - https://example.com/terms";
+
+        let siwe_message = message.parse::<SiweMessage>().unwrap();
+
+        assert_eq!(siwe_message.domain, "example.com");
+        assert_eq!(siwe_message.address, address!("C02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"));
+        assert_eq!(siwe_message.statement.as_deref(), Some("Sign in to the Ethereum Avatar Service."));
+        assert_eq!(siwe_message.chain_id, 137);
+        assert_eq!(siwe_message.nonce, "32891756a1b2c3d4");
+        assert!(siwe_message.expiration_time.is_some());
+        assert!(siwe_message.not_before.is_none());
+    }
+
+    #[test]
+    fn test_parse_rejects_unchecksummed_address() {
+        let message = "example.com wants you to sign in with your Ethereum account:\n\
+            0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2\n\
+            \n\
+            URI: https://example.com/login\n\
+            Version: 1\n\
+            Chain ID: 1\n\
+            Nonce: 32891756a1b2c3d4\n\
+            Issued At: 2024-06-01T12:00:00Z";
+
+        assert!(message.parse::<SiweMessage>().is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::io::AsyncWriteExt;
+
+// Directory the state of the services is persisted to
+static DATA_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
+    std::env::var("DATA_DIR").ok().filter(|dir| !dir.is_empty()).map_or_else(|| PathBuf::from("data"), PathBuf::from)
+});
+
+/// A file in `DATA_DIR` holding state that has to survive restarts.
+///
+/// Documents are replaced atomically through a temporary file, logs are appended as JSON lines.
+/// Callers serialize their writes, usually by holding the lock of the state being saved.
+#[derive(Debug, Clone)]
+pub struct Store {
+    path: PathBuf
+}
+
+impl Store {
+    pub fn new(file_name: &str) -> Self {
+        Self::at(DATA_DIR.join(file_name))
+    }
+
+    pub fn at(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Reads the document, `None` before it is first saved.
+    #[allow(clippy::missing_errors_doc)]
+    pub fn load<T: DeserializeOwned>(&self) -> eyre::Result<Option<T>> {
+        match std::fs::read(&self.path) {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into())
+        }
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn save<T: Serialize>(&self, value: &T) -> eyre::Result<()> {
+        self.create_dir().await?;
+
+        let temporary = self.path.with_extension("tmp");
+
+        tokio::fs::write(&temporary, serde_json::to_vec(value)?).await?;
+        tokio::fs::rename(&temporary, &self.path).await?;
+
+        Ok(())
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn append<T: Serialize>(&self, entry: &T) -> eyre::Result<()> {
+        self.create_dir().await?;
+
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+
+        let mut file = tokio::fs::OpenOptions::new().create(true).append(true).open(&self.path).await?;
+        file.write_all(&line).await?;
+
+        Ok(())
+    }
+
+    /// Reads the last `max` entries of the log and compacts it down to them.
+    ///
+    /// A line cut short by a crash is skipped.
+    #[allow(clippy::missing_errors_doc)]
+    pub fn load_log<T: DeserializeOwned + Serialize>(&self, max: usize) -> eyre::Result<Vec<T>> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into())
+        };
+
+        let mut entries: Vec<T> = content.lines().filter_map(|line| serde_json::from_str(line).ok()).collect();
+        entries.drain(..entries.len().saturating_sub(max));
+
+        let mut compacted = Vec::new();
+
+        for entry in &entries {
+            compacted.extend(serde_json::to_vec(entry)?);
+            compacted.push(b'\n');
+        }
+
+        let temporary = self.path.with_extension("tmp");
+
+        std::fs::write(&temporary, compacted)?;
+        std::fs::rename(&temporary, &self.path)?;
+
+        Ok(entries)
+    }
+
+    async fn create_dir(&self) -> std::io::Result<()> {
+        match self.path.parent() {
+            Some(parent) => tokio::fs::create_dir_all(parent).await,
+            None => Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::services::store::Store;
+
+    #[tokio::test]
+    async fn test_save_and_log_round_trip() {
+        let dir = std::env::temp_dir().join(format!("eas-api-store-{}", rand::random::<u64>()));
+
+        let document = Store::at(dir.join("document.json"));
+        assert_eq!(document.load::<Vec<u32>>().unwrap(), None);
+
+        document.save(&vec![1, 2]).await.unwrap();
+        document.save(&vec![3]).await.unwrap();
+        assert_eq!(document.load::<Vec<u32>>().unwrap(), Some(vec![3]));
+
+        let log = Store::at(dir.join("log.jsonl"));
+
+        for entry in 0..5u32 {
+            log.append(&entry).await.unwrap();
+        }
+
+        assert_eq!(log.load_log::<u32>(3).unwrap(), vec![2, 3, 4]);
+        assert_eq!(log.load_log::<u32>(10).unwrap(), vec![2, 3, 4]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use alloy::primitives::Address;
+use log::warn;
+use thiserror::Error;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::models::event::AvatarSetEvent;
+use crate::services::events::EventIndex;
+use crate::supported_networks::SupportedNetworks;
+
+// Wallets a single stream may follow
+pub const MAX_SUBSCRIBED_WALLETS: usize = 100;
+
+#[derive(Debug, Clone, Default)]
+pub struct Subscription {
+    pub wallets: HashSet<Address>,
+    // Empty means every network
+    pub networks: Vec<SupportedNetworks>
+}
+
+impl Subscription {
+    pub fn matches(&self, network: &SupportedNetworks, event: &AvatarSetEvent) -> bool {
+        self.wallets.contains(&event.wallet) && (self.networks.is_empty() || self.networks.contains(network))
+    }
+}
+
+/// Where a stream continues on one network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    // Replays the events from this block on
+    From(u64),
+    // Continues after the event at (block, log index)
+    After(u64, u64)
+}
+
+impl Position {
+    pub fn admits(&self, event: &AvatarSetEvent) -> bool {
+        match *self {
+            Position::From(block_number) => event.block_number >= block_number,
+            Position::After(block_number, log_index) => (event.block_number, event.log_index) > (block_number, log_index)
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("Invalid stream cursor: {0}")]
+pub struct InvalidCursor(String);
+
+/// Per-network stream positions, `network:block[:log_index],...`.
+///
+/// A bare block replays from that block, a log index resumes after that event.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cursor(pub HashMap<SupportedNetworks, Position>);
+
+impl FromStr for Cursor {
+    type Err = InvalidCursor;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut positions = HashMap::new();
+
+        for entry in s.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let invalid = || InvalidCursor(entry.to_string());
+
+            let parts: Vec<_> = entry.split(':').collect();
+
+            let (network, position) = match parts[..] {
+                [network, block_number] => (network, Position::From(block_number.parse().map_err(|_| invalid())?)),
+                [network, block_number, log_index] => {
+                    let (Ok(block_number), Ok(log_index)) = (block_number.parse(), log_index.parse()) else {
+                        return Err(invalid());
+                    };
+
+                    (network, Position::After(block_number, log_index))
+                }
+                _ => return Err(invalid())
+            };
+
+            positions.insert(network.parse::<SupportedNetworks>().map_err(|_| invalid())?, position);
+        }
+
+        Ok(Self(positions))
+    }
+}
+
+impl Display for Cursor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut entries: Vec<_> = self.0.iter()
+            .map(|(network, position)| {
+                let network = network.to_string().to_lowercase();
+
+                match position {
+                    Position::From(block_number) => format!("{network}:{block_number}"),
+                    Position::After(block_number, log_index) => format!("{network}:{block_number}:{log_index}")
+                }
+            })
+            .collect();
+
+        // Stable, so equal cursors render the same
+        entries.sort();
+
+        write!(f, "{}", entries.join(","))
+    }
+}
+
+/// `AvatarSet` events matching a subscription, the replayed backlog first and live events after.
+///
+/// Every event comes with the cursor to resume after it. Networks the requested cursor doesn't
+/// cover start at the live events.
+pub struct Feed {
+    receiver: broadcast::Receiver<(SupportedNetworks, AvatarSetEvent)>,
+    pub subscription: Subscription,
+    backlog: VecDeque<(SupportedNetworks, AvatarSetEvent)>,
+    // Positions delivered so far, live events before them were already replayed
+    positions: HashMap<SupportedNetworks, Position>,
+    // Positions once the backlog is drained, right after the replayed blocks
+    caught_up: HashMap<SupportedNetworks, Position>
+}
+
+impl Feed {
+    /// Subscribes to new events and replays the broadcast events the `cursor` hasn't seen yet.
+    ///
+    /// Every index is read locked while subscribing. Syncs broadcast under the write lock, so
+    /// each event is either replayed from the index or received live, never both or neither.
+    pub async fn new(events: &EventIndex, subscription: Subscription, cursor: &Cursor) -> Self {
+        let mut indexes = Vec::new();
+
+        for network in SupportedNetworks::all() {
+            let index = events.read(&network).await;
+            indexes.push((network, index));
+        }
+
+        let receiver = events.subscribe();
+
+        let mut backlog = VecDeque::new();
+        let mut positions = HashMap::new();
+        let mut caught_up = HashMap::new();
+
+        for (network, index) in &indexes {
+            let requested = cursor.0.get(network).copied();
+
+            let Some(broadcast_to) = index.broadcast_to() else {
+                // Nothing broadcast yet, every later event comes live
+                if let Some(position) = requested {
+                    positions.insert(network.clone(), position);
+                }
+
+                continue;
+            };
+
+            let live_from = Position::From(broadcast_to + 1);
+
+            let Some(position) = requested else {
+                positions.insert(network.clone(), live_from);
+                continue;
+            };
+
+            let replayed = index.events().iter()
+                .filter(|event| position.admits(event))
+                .take_while(|event| event.block_number <= broadcast_to)
+                .filter(|event| subscription.matches(network, event));
+
+            for event in replayed {
+                backlog.push_back((network.clone(), index.annotate(event)));
+            }
+
+            positions.insert(network.clone(), position);
+            caught_up.insert(network.clone(), live_from);
+        }
+
+        drop(indexes);
+
+        let mut feed = Self { receiver, subscription, backlog, positions, caught_up };
+        feed.catch_up();
+
+        feed
+    }
+
+    fn catch_up(&mut self) {
+        if self.backlog.is_empty() {
+            self.positions.extend(self.caught_up.drain());
+        }
+    }
+
+    fn advance(&mut self, network: &SupportedNetworks, event: &AvatarSetEvent) -> Cursor {
+        self.positions.insert(network.clone(), Position::After(event.block_number, event.log_index));
+        self.catch_up();
+
+        Cursor(self.positions.clone())
+    }
+
+    /// Waits for the next matching event and the cursor after it, `None` once the index is gone.
+    pub async fn next(&mut self) -> Option<(SupportedNetworks, AvatarSetEvent, Cursor)> {
+        if let Some((network, event)) = self.backlog.pop_front() {
+            let cursor = self.advance(&network, &event);
+
+            return Some((network, event, cursor));
+        }
+
+        loop {
+            match self.receiver.recv().await {
+                Ok((network, event)) => {
+                    let already_delivered = self.positions.get(&network)
+                        .is_some_and(|position| !position.admits(&event));
+
+                    if self.subscription.matches(&network, &event) && !already_delivered {
+                        let cursor = self.advance(&network, &event);
+
+                        return Some((network, event, cursor));
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!(target: "API", "Avatar stream subscriber lagged, {skipped} events skipped");
+                }
+                Err(RecvError::Closed) => return None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use alloy::primitives::{address, B256, U256};
+
+    use crate::models::event::AvatarSetEvent;
+    use crate::services::stream::{Cursor, Position, Subscription};
+    use crate::supported_networks::SupportedNetworks;
+
+    #[test]
+    fn test_subscription_matches() {
+        let wallet = address!("0000000000000000000000000000000000000001");
+
+        let event = AvatarSetEvent {
+            wallet,
+            token_address: address!("907808732079863886443057C65827a0F1c64357"),
+            token_id: U256::from(1),
+            block_number: 1,
+            block_hash: B256::ZERO,
+            transaction_hash: B256::ZERO,
+            log_index: 0,
+            timestamp: None,
+            confirmed: None,
+        };
+
+        let subscription = Subscription {
+            wallets: HashSet::from([wallet]),
+            networks: vec![SupportedNetworks::Polygon],
+        };
+
+        assert!(subscription.matches(&SupportedNetworks::Polygon, &event));
+        assert!(!subscription.matches(&SupportedNetworks::Ethereum, &event));
+
+        let other = AvatarSetEvent { wallet: address!("0000000000000000000000000000000000000002"), ..event };
+        assert!(!subscription.matches(&SupportedNetworks::Polygon, &other));
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor: Cursor = "polygon:105:3, base:2000".parse().unwrap();
+
+        assert_eq!(cursor.0[&SupportedNetworks::Polygon], Position::After(105, 3));
+        assert_eq!(cursor.0[&SupportedNetworks::Base], Position::From(2000));
+        assert_eq!(cursor.to_string(), "base:2000,polygon:105:3");
+        assert_eq!(cursor.to_string().parse::<Cursor>().unwrap(), cursor);
+
+        assert!("polygon".parse::<Cursor>().is_err());
+        assert!("polygon:x:1".parse::<Cursor>().is_err());
+        assert!("solana:1".parse::<Cursor>().is_err());
+    }
+
+    #[test]
+    fn test_position_admits() {
+        let event = |block_number: u64, log_index: u64| AvatarSetEvent {
+            wallet: address!("0000000000000000000000000000000000000001"),
+            token_address: address!("907808732079863886443057C65827a0F1c64357"),
+            token_id: U256::from(1),
+            block_number,
+            block_hash: B256::ZERO,
+            transaction_hash: B256::ZERO,
+            log_index,
+            timestamp: None,
+            confirmed: None,
+        };
+
+        // Resuming after an event skips it and the earlier ones of its block
+        assert!(!Position::After(10, 2).admits(&event(10, 2)));
+        assert!(!Position::After(10, 2).admits(&event(10, 1)));
+        assert!(Position::After(10, 2).admits(&event(10, 3)));
+        assert!(Position::After(10, 2).admits(&event(11, 0)));
+
+        assert!(Position::From(10).admits(&event(10, 0)));
+        assert!(!Position::From(10).admits(&event(9, 7)));
+    }
+}
@@ -0,0 +1,704 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use alloy::primitives::{hex, Address};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use log::{error, info, warn};
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::redirect::Policy;
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use thiserror::Error;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{RwLock, Semaphore};
+use tokio::task::JoinHandle;
+
+use crate::models::event::AvatarSetEvent;
+use crate::response::stream::AvatarChangeResponse;
+use crate::services::avatar::AvatarService;
+use crate::services::store::Store;
+use crate::services::stream::{Cursor, Position};
+use crate::services::whitelist::unix_timestamp;
+use crate::supported_networks::SupportedNetworks;
+
+const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(2);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+// Deliveries in progress at once across all webhooks, the dispatcher waits for a free slot
+// before starting the next one so a backlog of events is not spawned all at once
+const MAX_CONCURRENT_DELIVERIES: usize = 32;
+
+// Oldest entries are dropped beyond these sizes
+const MAX_DELIVERY_LOG: usize = 10_000;
+const MAX_DEAD_LETTERS: usize = 1_000;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Webhook URL must be an absolute http(s) URL")]
+    InvalidUrl,
+    #[error("Webhook host {0} could not be resolved")]
+    UnresolvableHost(String),
+    #[error("Webhook host {0} resolves to a non-public address")]
+    NonPublicHost(String),
+    #[error("Unknown webhook")]
+    NotFound,
+    #[error("Failed to persist webhooks: {0}")]
+    Persist(eyre::Report)
+}
+
+/// Events a webhook receives, every non-empty set has to match.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct WebhookFilter {
+    pub wallets: HashSet<Address>,
+    pub collections: HashSet<Address>,
+    pub networks: HashSet<SupportedNetworks>
+}
+
+impl WebhookFilter {
+    pub fn matches(&self, network: &SupportedNetworks, event: &AvatarSetEvent) -> bool {
+        (self.wallets.is_empty() || self.wallets.contains(&event.wallet))
+            && (self.collections.is_empty() || self.collections.contains(&event.token_address))
+            && (self.networks.is_empty() || self.networks.contains(network))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Webhook {
+    pub id: String,
+    // Name of the API key that registered the webhook
+    pub owner: String,
+    pub url: String,
+    #[serde(flatten)]
+    pub filter: WebhookFilter,
+    // Only revealed once, when the webhook is registered
+    #[serde(skip)]
+    pub secret: String,
+    pub created_at: DateTime<Utc>
+}
+
+// The persisted form of a webhook, which unlike the responses keeps the secret
+#[derive(Serialize, Deserialize)]
+struct StoredWebhook {
+    #[serde(flatten)]
+    webhook: Webhook,
+    secret: String
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Delivery {
+    pub id: String,
+    pub webhook_id: String,
+    // Stream cursor of the event, `network:block:log_index`
+    pub event_id: String,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub response_status: Option<u16>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeadLetter {
+    pub delivery_id: String,
+    pub webhook_id: String,
+    pub event_id: String,
+    pub payload: String,
+    pub error: Option<String>,
+    pub failed_at: DateTime<Utc>
+}
+
+// Files the webhooks, their deliveries and dead letters are persisted to
+#[derive(Debug, Clone)]
+pub struct WebhookStores {
+    pub webhooks: Store,
+    pub deliveries: Store,
+    pub dead_letters: Store
+}
+
+impl Default for WebhookStores {
+    fn default() -> Self {
+        Self {
+            webhooks: Store::new("webhooks.json"),
+            deliveries: Store::new("webhook_deliveries.jsonl"),
+            dead_letters: Store::new("webhook_dead_letters.jsonl"),
+        }
+    }
+}
+
+/// Registered webhooks and the log of their deliveries.
+///
+/// Everything is persisted when stores are given. Deliveries log every change of their state, a
+/// delivery still pending on restart is marked as failed.
+pub struct WebhookService {
+    client: reqwest::Client,
+    webhooks: RwLock<HashMap<String, Webhook>>,
+    deliveries: RwLock<VecDeque<Delivery>>,
+    dead_letters: RwLock<VecDeque<DeadLetter>>,
+    in_flight: Arc<Semaphore>,
+    stores: Option<WebhookStores>
+}
+
+impl Default for WebhookService {
+    fn default() -> Self {
+        Self {
+            // Redirects aren't followed, they could point past the address checks
+            client: reqwest::Client::builder()
+                .timeout(DELIVERY_TIMEOUT)
+                .redirect(Policy::none())
+                .dns_resolver(Arc::new(PublicResolver))
+                .build()
+                .unwrap(),
+            webhooks: RwLock::default(),
+            deliveries: RwLock::default(),
+            dead_letters: RwLock::default(),
+            in_flight: Arc::new(Semaphore::new(MAX_CONCURRENT_DELIVERIES)),
+            stores: None,
+        }
+    }
+}
+
+impl WebhookService {
+    /// Restores the webhooks, deliveries and dead letters saved in `stores`.
+    #[allow(clippy::missing_errors_doc)]
+    pub fn load(stores: WebhookStores) -> eyre::Result<Self> {
+        let webhooks: HashMap<_, _> = stores.webhooks.load::<Vec<StoredWebhook>>()?.unwrap_or_default().into_iter()
+            .map(|StoredWebhook { mut webhook, secret }| {
+                webhook.secret = secret;
+                (webhook.id.clone(), webhook)
+            })
+            .collect();
+
+        // Every attempt of a delivery appends its state, the last one wins
+        let mut deliveries: VecDeque<Delivery> = VecDeque::new();
+        let mut positions = HashMap::new();
+
+        for delivery in stores.deliveries.load_log::<Delivery>(MAX_DELIVERY_LOG * (MAX_ATTEMPTS as usize + 1))? {
+            match positions.get(&delivery.id) {
+                Some(&position) => deliveries[position] = delivery,
+                None => {
+                    positions.insert(delivery.id.clone(), deliveries.len());
+                    deliveries.push_back(delivery);
+                }
+            }
+        }
+
+        deliveries.drain(..deliveries.len().saturating_sub(MAX_DELIVERY_LOG));
+
+        for delivery in deliveries.iter_mut().filter(|delivery| delivery.status == DeliveryStatus::Pending) {
+            delivery.status = DeliveryStatus::Failed;
+            delivery.error = Some("Interrupted by a restart".to_string());
+        }
+
+        let dead_letters: VecDeque<DeadLetter> = stores.dead_letters.load_log::<DeadLetter>(MAX_DEAD_LETTERS)?.into();
+
+        Ok(Self {
+            webhooks: RwLock::new(webhooks),
+            deliveries: RwLock::new(deliveries),
+            dead_letters: RwLock::new(dead_letters),
+            stores: Some(stores),
+            ..Default::default()
+        })
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn register(&self, owner: &str, url: &str, filter: WebhookFilter) -> Result<Webhook, Error> {
+        let parsed_url = url.parse::<Url>().map_err(|_| Error::InvalidUrl)?;
+
+        if !matches!(parsed_url.scheme(), "http" | "https") {
+            return Err(Error::InvalidUrl);
+        }
+
+        check_host(&parsed_url).await?;
+
+        let webhook = Webhook {
+            id: hex::encode(rand::random::<[u8; 16]>()),
+            owner: owner.to_string(),
+            url: parsed_url.to_string(),
+            filter,
+            secret: hex::encode(rand::random::<[u8; 32]>()),
+            created_at: Utc::now(),
+        };
+
+        let mut webhooks = self.webhooks.write().await;
+
+        webhooks.insert(webhook.id.clone(), webhook.clone());
+
+        if let Err(err) = self.persist(&webhooks).await {
+            webhooks.remove(&webhook.id);
+            return Err(err);
+        }
+
+        Ok(webhook)
+    }
+
+    pub async fn list(&self, owner: &str) -> Vec<Webhook> {
+        let mut webhooks: Vec<_> = self.webhooks.read().await.values()
+            .filter(|webhook| webhook.owner == owner)
+            .cloned()
+            .collect();
+
+        webhooks.sort_by_key(|webhook| webhook.created_at);
+
+        webhooks
+    }
+
+    /// The webhook with `id`, hidden from everyone but its owner.
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn get(&self, owner: &str, id: &str) -> Result<Webhook, Error> {
+        self.webhooks.read().await.get(id)
+            .filter(|webhook| webhook.owner == owner)
+            .cloned()
+            .ok_or(Error::NotFound)
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub async fn remove(&self, owner: &str, id: &str) -> Result<(), Error> {
+        let mut webhooks = self.webhooks.write().await;
+
+        if !webhooks.get(id).is_some_and(|webhook| webhook.owner == owner) {
+            return Err(Error::NotFound);
+        }
+
+        let removed = webhooks.remove(id);
+
+        if let Err(err) = self.persist(&webhooks).await {
+            webhooks.extend(removed.map(|webhook| (webhook.id.clone(), webhook)));
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    async fn persist(&self, webhooks: &HashMap<String, Webhook>) -> Result<(), Error> {
+        let Some(stores) = &self.stores else {
+            return Ok(());
+        };
+
+        let stored: Vec<_> = webhooks.values()
+            .map(|webhook| StoredWebhook { webhook: webhook.clone(), secret: webhook.secret.clone() })
+            .collect();
+
+        stores.webhooks.save(&stored).await.map_err(Error::Persist)
+    }
+
+    // Failing to log a delivery doesn't stop it, callers hold the lock of the logged state
+    async fn append<T: Serialize>(&self, store: impl FnOnce(&WebhookStores) -> &Store, entry: &T) {
+        let Some(stores) = &self.stores else {
+            return;
+        };
+
+        if let Err(err) = store(stores).append(entry).await {
+            error!(target: "API", "Failed to persist webhook delivery log: {err}");
+        }
+    }
+
+    /// Deliveries of the webhook, most recent first.
+    pub async fn deliveries(&self, webhook_id: &str) -> Vec<Delivery> {
+        self.deliveries.read().await.iter().rev()
+            .filter(|delivery| delivery.webhook_id == webhook_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Deliveries of the webhook that ran out of attempts, most recent first.
+    pub async fn dead_letters(&self, webhook_id: &str) -> Vec<DeadLetter> {
+        self.dead_letters.read().await.iter().rev()
+            .filter(|dead_letter| dead_letter.webhook_id == webhook_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Posts every newly indexed `AvatarSet` event to the matching webhooks.
+    pub fn spawn_dispatcher(self: &Arc<Self>, avatar_service: Arc<AvatarService>) -> JoinHandle<()> {
+        let webhook_service = self.clone();
+        let mut receiver = avatar_service.events.subscribe();
+
+        tokio::spawn(async move {
+            loop {
+                let (network, event) = match receiver.recv().await {
+                    Ok(indexed) => indexed,
+                    Err(RecvError::Lagged(skipped)) => {
+                        error!(target: "API", "Webhook dispatcher lagged, {skipped} events not delivered");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => return
+                };
+
+                let targets: Vec<Webhook> = webhook_service.webhooks.read().await.values()
+                    .filter(|webhook| webhook.filter.matches(&network, &event))
+                    .cloned()
+                    .collect();
+
+                if targets.is_empty() {
+                    continue;
+                }
+
+                let avatar = avatar_service.resolve_event(&network, &event).await;
+                let cursor = Cursor(HashMap::from([(network.clone(), Position::After(event.block_number, event.log_index))]));
+
+                let change = AvatarChangeResponse {
+                    network: network.to_string().to_lowercase(),
+                    event,
+                    avatar,
+                    cursor: cursor.to_string(),
+                };
+
+                let payload = match serde_json::to_string(&change) {
+                    Ok(payload) => payload,
+                    Err(err) => {
+                        error!(target: "API", "Failed to encode webhook payload: {err}");
+                        continue;
+                    }
+                };
+
+                for webhook in targets {
+                    // Held until the delivery succeeded or was dead lettered, retries included
+                    let Ok(permit) = webhook_service.in_flight.clone().acquire_owned().await else {
+                        return;
+                    };
+
+                    let webhook_service = webhook_service.clone();
+                    let (event_id, payload) = (change.cursor.clone(), payload.clone());
+
+                    tokio::spawn(async move {
+                        webhook_service.deliver(webhook, event_id, payload).await;
+                        drop(permit);
+                    });
+                }
+            }
+        })
+    }
+
+    /// Posts `payload` with exponential backoff, moving it to the dead letters after the last attempt.
+    async fn deliver(&self, webhook: Webhook, event_id: String, payload: String) {
+        let now = Utc::now();
+
+        let delivery = Delivery {
+            id: hex::encode(rand::random::<[u8; 16]>()),
+            webhook_id: webhook.id.clone(),
+            event_id,
+            status: DeliveryStatus::Pending,
+            attempts: 0,
+            response_status: None,
+            error: None,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let delivery_id = delivery.id.clone();
+
+        {
+            let mut deliveries = self.deliveries.write().await;
+
+            if deliveries.len() >= MAX_DELIVERY_LOG {
+                deliveries.pop_front();
+            }
+
+            deliveries.push_back(delivery.clone());
+
+            self.append(|stores| &stores.deliveries, &delivery).await;
+        }
+
+        let mut backoff = INITIAL_BACKOFF;
+
+        for attempt in 1..=MAX_ATTEMPTS {
+            let (response_status, error) = match self.post(&webhook, &delivery_id, &payload).await {
+                Ok(status) if status.is_success() => (Some(status), None),
+                Ok(status) => (Some(status), Some(format!("Responded with {status}"))),
+                Err(err) => (None, Some(err.to_string()))
+            };
+
+            let delivered = error.is_none();
+
+            self.update(&delivery_id, |delivery| {
+                delivery.attempts = attempt;
+                delivery.response_status = response_status.map(|status| status.as_u16());
+                delivery.error.clone_from(&error);
+                delivery.updated_at = Utc::now();
+
+                if delivered {
+                    delivery.status = DeliveryStatus::Delivered;
+                }
+            }).await;
+
+            if delivered {
+                info!(target: "API", "Delivered {} to webhook {}", delivery.event_id, webhook.id);
+                return;
+            }
+
+            warn!(target: "API", "Delivery {delivery_id} to webhook {} failed (attempt {attempt}/{MAX_ATTEMPTS}): {}", webhook.id, error.unwrap_or_default());
+
+            if attempt < MAX_ATTEMPTS {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+        }
+
+        let mut last_error = None;
+
+        self.update(&delivery_id, |delivery| {
+            delivery.status = DeliveryStatus::Failed;
+            last_error.clone_from(&delivery.error);
+        }).await;
+
+        let dead_letter = DeadLetter {
+            delivery_id,
+            webhook_id: webhook.id,
+            event_id: delivery.event_id,
+            payload,
+            error: last_error,
+            failed_at: Utc::now(),
+        };
+
+        {
+            let mut dead_letters = self.dead_letters.write().await;
+
+            if dead_letters.len() >= MAX_DEAD_LETTERS {
+                dead_letters.pop_front();
+            }
+
+            dead_letters.push_back(dead_letter.clone());
+
+            self.append(|stores| &stores.dead_letters, &dead_letter).await;
+        }
+    }
+
+    async fn post(&self, webhook: &Webhook, delivery_id: &str, payload: &str) -> eyre::Result<StatusCode> {
+        // Literal addresses skip the resolver, so the host is checked on every attempt
+        check_host(&webhook.url.parse::<Url>()?).await?;
+
+        let timestamp = unix_timestamp();
+
+        let response = self.client.post(&webhook.url)
+            .header(CONTENT_TYPE, "application/json")
+            .header("X-Webhook-Id", &webhook.id)
+            .header("X-Webhook-Delivery", delivery_id)
+            .header("X-Webhook-Timestamp", timestamp.to_string())
+            .header("X-Webhook-Signature", format!("sha256={}", sign(&webhook.secret, timestamp, payload)))
+            .body(payload.to_string())
+            .send()
+            .await?;
+
+        Ok(response.status())
+    }
+
+    async fn update(&self, delivery_id: &str, update: impl FnOnce(&mut Delivery)) {
+        let mut deliveries = self.deliveries.write().await;
+
+        if let Some(delivery) = deliveries.iter_mut().rev().find(|delivery| delivery.id == delivery_id) {
+            update(delivery);
+
+            self.append(|stores| &stores.deliveries, &*delivery).await;
+        }
+    }
+}
+
+/// Resolves webhook hosts, failing for any that has a non-public address.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addresses = resolve_public(name.as_str(), 0).await?;
+            let addresses: Addrs = Box::new(addresses.into_iter());
+
+            Ok(addresses)
+        })
+    }
+}
+
+async fn check_host(url: &Url) -> Result<(), Error> {
+    let Some(host) = url.host_str() else {
+        return Err(Error::InvalidUrl);
+    };
+
+    resolve_public(host, url.port_or_known_default().unwrap_or_default()).await.map(|_| ())
+}
+
+async fn resolve_public(host: &str, port: u16) -> Result<Vec<SocketAddr>, Error> {
+    // IPv6 literals come bracketed from URLs
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+
+    let addresses: Vec<_> = match host.parse::<IpAddr>() {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => tokio::net::lookup_host((host, port)).await
+            .map_err(|_| Error::UnresolvableHost(host.to_string()))?
+            .collect()
+    };
+
+    if addresses.is_empty() {
+        return Err(Error::UnresolvableHost(host.to_string()));
+    }
+
+    // Any private address is refused, the client could pick it over the public ones
+    if !addresses.iter().all(|address| is_public(address.ip())) {
+        return Err(Error::NonPublicHost(host.to_string()));
+    }
+
+    Ok(addresses)
+}
+
+/// Whether `ip` is globally routable, webhooks mustn't reach into the internal network.
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => {
+            // Mapped and NAT64 addresses reach the embedded IPv4 one
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_v4(ip);
+            }
+
+            let segments = ip.segments();
+
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                return is_public_v4(Ipv4Addr::from((u32::from(segments[6]) << 16) | u32::from(segments[7])));
+            }
+
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // Unique local fc00::/7 and link-local fe80::/10
+                || (segments[0] & 0xfe00) == 0xfc00
+                || (segments[0] & 0xffc0) == 0xfe80
+                // Documentation 2001:db8::/32
+                || (segments[0] == 0x2001 && segments[1] == 0xdb8))
+        }
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+
+    !(ip.is_unspecified()
+        || ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // "This network" 0.0.0.0/8 and reserved 240.0.0.0/4
+        || a == 0
+        || a >= 240
+        // Shared address space 100.64.0.0/10
+        || (a == 100 && (b & 0xc0) == 64)
+        // IETF protocol assignments 192.0.0.0/24 and benchmarking 198.18.0.0/15
+        || (a == 192 && b == 0 && ip.octets()[2] == 0)
+        || (a == 198 && (b & 0xfe) == 18))
+}
+
+/// Hex encoded HMAC-SHA256 of `<timestamp>.<payload>`, keyed with the webhook secret.
+///
+/// Receivers recompute it from the `X-Webhook-Timestamp` header and the raw body, the timestamp
+/// lets them reject replayed deliveries.
+pub fn sign(secret: &str, timestamp: u64, payload: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+
+    mac.update(format!("{timestamp}.").as_bytes());
+    mac.update(payload.as_bytes());
+
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::net::IpAddr;
+
+    use alloy::primitives::{address, B256, U256};
+
+    use crate::models::event::AvatarSetEvent;
+    use crate::services::store::Store;
+    use crate::services::webhook::{is_public, sign, Error, WebhookFilter, WebhookService, WebhookStores};
+    use crate::supported_networks::SupportedNetworks;
+
+    #[test]
+    fn test_sign() {
+        let signature = sign("secret", 1_700_000_000, r#"{"hello":"world"}"#);
+
+        assert_eq!(signature, "654f06c856baf080af3fa272934823257a542d35cf1f88099338f850a60601a4");
+    }
+
+    #[test]
+    fn test_filter_matches() {
+        let event = AvatarSetEvent {
+            wallet: address!("0000000000000000000000000000000000000001"),
+            token_address: address!("907808732079863886443057C65827a0F1c64357"),
+            token_id: U256::from(1),
+            block_number: 1,
+            block_hash: B256::ZERO,
+            transaction_hash: B256::ZERO,
+            log_index: 0,
+            timestamp: None,
+            confirmed: None,
+        };
+
+        assert!(WebhookFilter::default().matches(&SupportedNetworks::Base, &event));
+
+        let filter = WebhookFilter {
+            collections: HashSet::from([event.token_address]),
+            networks: HashSet::from([SupportedNetworks::Polygon]),
+            ..Default::default()
+        };
+
+        assert!(filter.matches(&SupportedNetworks::Polygon, &event));
+        assert!(!filter.matches(&SupportedNetworks::Base, &event));
+    }
+
+    #[test]
+    fn test_is_public() {
+        let public = ["93.184.215.14", "8.8.8.8", "2606:4700:4700::1111"];
+        let internal = [
+            "127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "100.64.0.1",
+            "0.0.0.0", "255.255.255.255", "198.18.0.1", "::1", "::", "fd00::1", "fe80::1",
+            "::ffff:127.0.0.1", "64:ff9b::a9fe:a9fe"
+        ];
+
+        for ip in public {
+            assert!(is_public(ip.parse::<IpAddr>().unwrap()), "{ip}");
+        }
+
+        for ip in internal {
+            assert!(!is_public(ip.parse::<IpAddr>().unwrap()), "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_webhooks_are_persisted() {
+        let dir = std::env::temp_dir().join(format!("eas-api-webhooks-{}", rand::random::<u64>()));
+
+        let stores = WebhookStores {
+            webhooks: Store::at(dir.join("webhooks.json")),
+            deliveries: Store::at(dir.join("deliveries.jsonl")),
+            dead_letters: Store::at(dir.join("dead_letters.jsonl")),
+        };
+
+        let webhook_service = WebhookService::load(stores.clone()).unwrap();
+
+        assert!(matches!(webhook_service.register("ops", "http://127.0.0.1:8080/hook", WebhookFilter::default()).await, Err(Error::NonPublicHost(_))));
+        assert!(matches!(webhook_service.register("ops", "http://[::1]/hook", WebhookFilter::default()).await, Err(Error::NonPublicHost(_))));
+
+        let webhook = webhook_service.register("ops", "https://93.184.215.14/hook", WebhookFilter::default()).await.unwrap();
+
+        let restored = WebhookService::load(stores).unwrap();
+        let restored_webhook = restored.get("ops", &webhook.id).await.unwrap();
+
+        assert_eq!(restored_webhook.url, webhook.url);
+        assert_eq!(restored_webhook.secret, webhook.secret);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
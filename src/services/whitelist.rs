@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use alloy::primitives::Address;
+use reqwest::header::{HeaderName, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::models::avatar::AvatarCollection;
+use crate::models::whitelist;
+use crate::response::whitelist::{WhitelistCollectionResponse, WhitelistReloadResponse};
+use crate::services::avatar::VerifiedCollections;
+use crate::supported_networks::SupportedNetworks;
+
+const DEFAULT_WHITELIST_URL: &str = "https://raw.githubusercontent.com/ethereum-avatar-service/eas-api-whitelist/main/collections.json";
+const DEFAULT_REFRESH_INTERVAL: u64 = 600;
+
+pub static WHITELIST_URL: LazyLock<String> = LazyLock::new(|| {
+    std::env::var("WHITELIST_URL").unwrap_or_else(|_| DEFAULT_WHITELIST_URL.to_string())
+});
+
+// Seconds between background refreshes, 0 disables the refresh task
+pub static WHITELIST_REFRESH_INTERVAL: LazyLock<Option<Duration>> = LazyLock::new(|| {
+    let seconds = std::env::var("WHITELIST_REFRESH_INTERVAL")
+        .map(|value| value.parse::<u64>().expect("WHITELIST_REFRESH_INTERVAL must be a number of seconds"))
+        .unwrap_or(DEFAULT_REFRESH_INTERVAL);
+
+    (seconds > 0).then(|| Duration::from_secs(seconds))
+});
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Failed to fetch whitelist: {0}")]
+    Fetch(#[source] reqwest::Error),
+    #[error("Whitelist source responded with {0}")]
+    Status(StatusCode),
+    #[error("Failed to decode whitelist: {0}")]
+    Decode(#[source] reqwest::Error),
+    #[error("Whitelist contains no valid collections")]
+    Empty
+}
+
+/// HTTP validators of the last successfully applied whitelist, sent back as conditional headers.
+#[derive(Default, Clone, Serialize)]
+pub struct CacheValidators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>
+}
+
+#[derive(Default, Clone, Serialize)]
+pub struct WhitelistStatus {
+    pub validators: CacheValidators,
+    pub last_checked: Option<u64>,
+    pub last_success: Option<u64>,
+    pub last_failure: Option<u64>,
+    pub last_error: Option<String>
+}
+
+impl WhitelistStatus {
+    pub fn record_success(&mut self, validators: Option<CacheValidators>) {
+        let now = unix_timestamp();
+
+        self.last_checked = Some(now);
+        self.last_success = Some(now);
+
+        if let Some(validators) = validators {
+            self.validators = validators;
+        }
+    }
+
+    pub fn record_failure(&mut self, error: &Error) {
+        let now = unix_timestamp();
+
+        self.last_checked = Some(now);
+        self.last_failure = Some(now);
+        self.last_error = Some(error.to_string());
+    }
+}
+
+pub enum Fetched {
+    NotModified,
+    Modified {
+        verified_collections: VerifiedCollections,
+        warnings: Vec<String>,
+        validators: CacheValidators
+    }
+}
+
+/// Downloads the whitelist and validates it into a fresh `VerifiedCollections` map.
+///
+/// When `validators` are given the request is conditional and may come back as `NotModified`.
+/// Entries with unknown networks, unparsable addresses or duplicate contracts are skipped and
+/// reported as warnings.
+#[allow(clippy::missing_errors_doc)]
+pub async fn fetch(url: &str, validators: Option<&CacheValidators>) -> Result<Fetched, Error> {
+    let mut request = reqwest::Client::new().get(url);
+
+    if let Some(validators) = validators {
+        if let Some(etag) = &validators.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let response = request.send().await.map_err(Error::Fetch)?;
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(Fetched::NotModified);
+    }
+
+    if !response.status().is_success() {
+        return Err(Error::Status(response.status()));
+    }
+
+    let header = |name: HeaderName| response.headers().get(name).and_then(|value| value.to_str().ok()).map(ToString::to_string);
+
+    let validators = CacheValidators {
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+    };
+
+    let collections = response.json::<whitelist::Collections>().await.map_err(Error::Decode)?;
+
+    let (verified_collections, warnings) = validate(collections);
+
+    if verified_collections.values().all(HashMap::is_empty) {
+        return Err(Error::Empty);
+    }
+
+    Ok(Fetched::Modified { verified_collections, warnings, validators })
+}
+
+pub fn validate(collections: whitelist::Collections) -> (VerifiedCollections, Vec<String>) {
+    let mut verified_collections = VerifiedCollections::new();
+    let mut warnings = Vec::new();
+
+    for (network, network_collections) in collections.0 {
+        let Ok(chain) = network.parse::<SupportedNetworks>() else {
+            warnings.push(format!("Unknown network '{network}' ({} collections skipped)", network_collections.len()));
+            continue;
+        };
+
+        let entry = verified_collections.entry(chain).or_default();
+
+        for collection in network_collections {
+            let Ok(address) = collection.contract.parse::<Address>() else {
+                warnings.push(format!("Invalid contract address '{}' on {network}", collection.contract));
+                continue;
+            };
+
+            if entry.contains_key(&address) {
+                warnings.push(format!("Duplicate contract {address} on {network}, keeping first entry"));
+                continue;
+            }
+
+            entry.insert(address, AvatarCollection {
+                name: Some(collection.name),
+                author: Some(collection.author),
+                website: Some(collection.website),
+                opensea: collection.opensea,
+                verified: true,
+                ..Default::default()
+            });
+        }
+    }
+
+    (verified_collections, warnings)
+}
+
+pub fn diff(old: &VerifiedCollections, new: &VerifiedCollections) -> WhitelistReloadResponse {
+    let mut response = WhitelistReloadResponse::default();
+
+    for network in SupportedNetworks::all() {
+        let old_collections = old.get(&network);
+        let new_collections = new.get(&network);
+
+        for (address, collection) in new_collections.into_iter().flatten() {
+            match old_collections.and_then(|collections| collections.get(address)) {
+                None => response.added.push(WhitelistCollectionResponse::new(&network, address, collection)),
+                Some(previous) if previous != collection => response.changed.push(WhitelistCollectionResponse::new(&network, address, collection)),
+                Some(_) => {}
+            }
+        }
+
+        for (address, collection) in old_collections.into_iter().flatten() {
+            if !new_collections.is_some_and(|collections| collections.contains_key(address)) {
+                response.removed.push(WhitelistCollectionResponse::new(&network, address, collection));
+            }
+        }
+    }
+
+    response
+}
+
+/// Collections whose name or author contains `query` (case-insensitive), ordered by name.
+pub fn search<'a>(collections: &'a HashMap<Address, AvatarCollection>, query: Option<&str>) -> Vec<(&'a Address, &'a AvatarCollection)> {
+    let query = query.map(str::to_lowercase);
+
+    let contains = |field: &Option<String>, query: &str| {
+        field.as_ref().is_some_and(|value| value.to_lowercase().contains(query))
+    };
+
+    let mut matches: Vec<_> = collections.iter()
+        .filter(|(_, collection)| {
+            query.as_deref().is_none_or(|query| contains(&collection.name, query) || contains(&collection.author, query))
+        })
+        .collect();
+
+    matches.sort_by(|(a_address, a), (b_address, b)| a.name.cmp(&b.name).then(a_address.cmp(b_address)));
+
+    matches
+}
+
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use alloy::primitives::address;
+    use axum::http::header::{ETAG, IF_NONE_MATCH};
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::response::IntoResponse;
+    use axum::routing::get;
+    use axum::Router;
+
+    use crate::models::whitelist::{Collection, Collections};
+    use crate::services::avatar::AvatarService;
+    use crate::services::whitelist::{diff, search, validate};
+    use crate::supported_networks::SupportedNetworks;
+
+    fn collection(contract: &str, name: &str) -> Collection {
+        Collection {
+            contract: contract.to_string(),
+            name: name.to_string(),
+            author: "author".to_string(),
+            website: "https://example.com".to_string(),
+            opensea: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_reports_invalid_entries() {
+        let collections = Collections([
+            ("Polygon".to_string(), vec![
+                collection("0x907808732079863886443057C65827a0F1c64357", "First"),
+                collection("0x907808732079863886443057C65827a0F1c64357", "Duplicate"),
+                collection("not-an-address", "Invalid"),
+            ]),
+            ("Solana".to_string(), vec![collection("0x907808732079863886443057C65827a0F1c64357", "Unknown")]),
+        ].into());
+
+        let (verified_collections, warnings) = validate(collections);
+
+        let polygon = &verified_collections[&SupportedNetworks::Polygon];
+        assert_eq!(polygon.len(), 1);
+        assert_eq!(polygon[&address!("907808732079863886443057C65827a0F1c64357")].name, Some("First".to_string()));
+        assert_eq!(warnings.len(), 3);
+    }
+
+    #[test]
+    fn test_diff() {
+        let (old, _) = validate(Collections([
+            ("ethereum".to_string(), vec![
+                collection("0x0000000000000000000000000000000000000001", "Removed"),
+                collection("0x0000000000000000000000000000000000000002", "Before"),
+            ]),
+        ].into()));
+
+        let (new, _) = validate(Collections([
+            ("ethereum".to_string(), vec![
+                collection("0x0000000000000000000000000000000000000002", "After"),
+                collection("0x0000000000000000000000000000000000000003", "Added"),
+            ]),
+        ].into()));
+
+        let response = diff(&old, &new);
+
+        assert_eq!(response.added.len(), 1);
+        assert_eq!(response.added[0].contract, address!("0000000000000000000000000000000000000003"));
+        assert_eq!(response.removed.len(), 1);
+        assert_eq!(response.removed[0].contract, address!("0000000000000000000000000000000000000001"));
+        assert_eq!(response.changed.len(), 1);
+        assert_eq!(response.changed[0].contract, address!("0000000000000000000000000000000000000002"));
+    }
+
+    #[test]
+    fn test_search() {
+        let (collections, _) = validate(Collections([
+            ("polygon".to_string(), vec![
+                collection("0x0000000000000000000000000000000000000001", "Punks"),
+                collection("0x0000000000000000000000000000000000000002", "Apes"),
+                collection("0x0000000000000000000000000000000000000003", "Space Punks"),
+            ]),
+        ].into()));
+
+        let polygon = &collections[&SupportedNetworks::Polygon];
+
+        let names: Vec<_> = search(polygon, Some("PUNK")).into_iter().filter_map(|(_, collection)| collection.name.clone()).collect();
+        assert_eq!(names, vec!["Punks".to_string(), "Space Punks".to_string()]);
+
+        assert_eq!(search(polygon, None).len(), 3);
+        assert_eq!(search(polygon, Some("author")).len(), 3);
+    }
+
+    fn whitelist_json(contract: &str, name: &str) -> String {
+        format!(r#"{{"polygon": [{{"contract": "{contract}", "name": "{name}", "author": "author", "website": "https://example.com"}}]}}"#)
+    }
+
+    #[tokio::test]
+    async fn test_conditional_reload() {
+        // ETag and body the stub source serves
+        let source = Arc::new(Mutex::new(("\"v1\"".to_string(), whitelist_json("0x0000000000000000000000000000000000000001", "First"))));
+        let served = source.clone();
+
+        let app = Router::new().route("/collections.json", get(move |headers: HeaderMap| {
+            let (etag, body) = served.lock().unwrap().clone();
+
+            async move {
+                if headers.get(IF_NONE_MATCH).is_some_and(|value| value == etag.as_str()) {
+                    return StatusCode::NOT_MODIFIED.into_response();
+                }
+
+                ([(ETAG, etag)], body).into_response()
+            }
+        }));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/collections.json", listener.local_addr().unwrap());
+
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let avatar_service = AvatarService::default();
+
+        let response = avatar_service.reload_verified_collections_from(&url, true).await.unwrap();
+        assert!(response.modified);
+        assert_eq!(response.added.len(), 1);
+        assert_eq!(avatar_service.whitelist_status.read().await.validators.etag.as_deref(), Some("\"v1\""));
+
+        // The stored ETag comes back as If-None-Match and the source answers 304
+        let response = avatar_service.reload_verified_collections_from(&url, true).await.unwrap();
+        assert!(!response.modified);
+        assert!(response.added.is_empty());
+        assert_eq!(avatar_service.cache.verified_collections.read().await[&SupportedNetworks::Polygon].len(), 1);
+        assert!(avatar_service.whitelist_status.read().await.last_error.is_none());
+
+        *source.lock().unwrap() = ("\"v2\"".to_string(), whitelist_json("0x0000000000000000000000000000000000000002", "Second"));
+
+        let response = avatar_service.reload_verified_collections_from(&url, true).await.unwrap();
+        assert!(response.modified);
+        assert_eq!(response.added[0].contract, address!("0000000000000000000000000000000000000002"));
+        assert_eq!(response.removed[0].contract, address!("0000000000000000000000000000000000000001"));
+        assert_eq!(avatar_service.whitelist_status.read().await.validators.etag.as_deref(), Some("\"v2\""));
+
+        // Unconditional reloads always download the whitelist
+        let response = avatar_service.reload_verified_collections_from(&url, false).await.unwrap();
+        assert!(response.modified);
+        assert!(response.added.is_empty() && response.removed.is_empty() && response.changed.is_empty());
+    }
+}
@@ -0,0 +1,40 @@
+use std::sync::Arc;
+
+use axum::extract::FromRef;
+
+use crate::services::avatar::AvatarService;
+use crate::services::relayer::RelayerService;
+use crate::services::siwe::SessionService;
+use crate::services::webhook::WebhookService;
+
+#[derive(Clone)]
+pub struct AppState {
+    pub avatar_service: Arc<AvatarService>,
+    pub session_service: Arc<SessionService>,
+    pub relayer_service: Arc<RelayerService>,
+    pub webhook_service: Arc<WebhookService>
+}
+
+impl FromRef<AppState> for Arc<AvatarService> {
+    fn from_ref(state: &AppState) -> Self {
+        state.avatar_service.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<SessionService> {
+    fn from_ref(state: &AppState) -> Self {
+        state.session_service.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<RelayerService> {
+    fn from_ref(state: &AppState) -> Self {
+        state.relayer_service.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<WebhookService> {
+    fn from_ref(state: &AppState) -> Self {
+        state.webhook_service.clone()
+    }
+}
@@ -1,5 +1,11 @@
+use std::str::FromStr;
+
+use alloy::primitives::{Address, U256};
 use serde::{Deserialize, Serialize};
 use strum::{Display, EnumIter, IntoEnumIterator};
+use thiserror::Error;
+
+use crate::models::nft::TokenStandard;
 
 #[derive(Deserialize, Serialize, Debug, Display, PartialEq, Eq, Hash, EnumIter, Clone)]
 pub enum SupportedNetworks {
@@ -13,4 +19,98 @@ impl SupportedNetworks {
     pub fn all() -> Vec<Self> {
         SupportedNetworks::iter().collect()
     }
-}
\ No newline at end of file
+
+    pub fn chain_id(&self) -> u64 {
+        match self {
+            SupportedNetworks::Ethereum => 1,
+            SupportedNetworks::Sepolia => 11_155_111,
+            SupportedNetworks::Polygon => 137,
+            SupportedNetworks::Base => 8453,
+        }
+    }
+
+    pub fn from_chain_id(chain_id: u64) -> Option<Self> {
+        SupportedNetworks::iter().find(|network| network.chain_id() == chain_id)
+    }
+
+    /// CAIP-2 chain id, e.g. `eip155:1`.
+    pub fn caip2(&self) -> String {
+        format!("eip155:{}", self.chain_id())
+    }
+
+    /// CAIP-10 account id of `address` on this network.
+    pub fn caip10(&self, address: &Address) -> String {
+        format!("{}:{address}", self.caip2())
+    }
+
+    /// CAIP-19 asset id of an NFT on this network, e.g. `eip155:1/erc721:0x…/42`.
+    pub fn caip19(&self, standard: TokenStandard, token_address: &Address, token_id: U256) -> String {
+        format!("{}/{}:{token_address}/{token_id}", self.caip2(), standard.caip19_namespace())
+    }
+
+    /// Parses comma separated networks, keeping the first occurrence of each.
+    #[allow(clippy::missing_errors_doc)]
+    pub fn parse_list(s: &str) -> Result<Vec<Self>, UnknownNetwork> {
+        let mut networks = Vec::new();
+
+        for network in s.split(',').map(str::trim).filter(|network| !network.is_empty()) {
+            let network = network.parse::<Self>()?;
+
+            if !networks.contains(&network) {
+                networks.push(network);
+            }
+        }
+
+        Ok(networks)
+    }
+
+    /// Accepted spellings of every network, for error messages.
+    pub fn valid_values() -> String {
+        SupportedNetworks::iter()
+            .map(|network| format!("{} ({}, {})", network.to_string().to_lowercase(), network.chain_id(), network.caip2()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("Unknown network '{0}', valid networks are {}", SupportedNetworks::valid_values())]
+pub struct UnknownNetwork(pub String);
+
+impl FromStr for SupportedNetworks {
+    type Err = UnknownNetwork;
+
+    /// Accepts names in any case, chain ids (`137`) and CAIP-2 ids (`eip155:137`).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = s.trim().to_lowercase();
+
+        if let Ok(chain_id) = value.strip_prefix("eip155:").unwrap_or(&value).parse::<u64>() {
+            return Self::from_chain_id(chain_id).ok_or_else(|| UnknownNetwork(s.to_string()));
+        }
+
+        match value.as_str() {
+            "mainnet" | "ethereum" => Ok(SupportedNetworks::Ethereum),
+            "sepolia" => Ok(SupportedNetworks::Sepolia),
+            "polygon" => Ok(SupportedNetworks::Polygon),
+            "base" => Ok(SupportedNetworks::Base),
+            _ => Err(UnknownNetwork(s.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::supported_networks::SupportedNetworks;
+
+    #[test]
+    fn test_parse_list() {
+        let networks = SupportedNetworks::parse_list("Ethereum, 8453,eip155:137,ethereum").unwrap();
+
+        assert_eq!(networks, vec![SupportedNetworks::Ethereum, SupportedNetworks::Base, SupportedNetworks::Polygon]);
+
+        let err = SupportedNetworks::parse_list("ethereum,eip155:10").unwrap_err();
+
+        assert_eq!(err.0, "eip155:10");
+        assert!(err.to_string().contains("polygon (137, eip155:137)"));
+    }
+}